@@ -0,0 +1,27 @@
+use std::{env, path::Path};
+
+/// Points the sqlx query macros at the offline query cache for the enabled database backend.
+///
+/// Each backend has its own directory in `.sqlx`, as SQLite and Postgres share query text.
+/// Builds with `DATABASE_URL` set also save their queries there, so a backend's cache is
+/// refreshed by removing its query files and building against a migrated database.
+fn main() {
+    println!("cargo:rerun-if-env-changed=SQLX_OFFLINE_DIR");
+
+    // A directory set explicitly, such as by `cargo sqlx prepare`, is used as-is.
+    if env::var_os("SQLX_OFFLINE_DIR").is_some() {
+        return;
+    }
+
+    let backend = if env::var_os("CARGO_FEATURE_SQLITE").is_some() {
+        "sqlite"
+    } else if env::var_os("CARGO_FEATURE_POSTGRES").is_some() {
+        "postgres"
+    } else {
+        "mysql"
+    };
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("cargo sets the manifest directory");
+    let dir = Path::new(&manifest_dir).join(".sqlx").join(backend);
+    println!("cargo:rustc-env=SQLX_OFFLINE_DIR={}", dir.display());
+}
@@ -0,0 +1,142 @@
+//! A client for the pancake server-to-server API, used by game servers.
+//!
+//! The HTTP client is behind the default `http` feature.
+//! Without it, only the request and response types are built.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+#[cfg(feature = "http")]
+use anyhow::{anyhow, Context};
+
+/// Signs a message with HMAC-SHA256, returning the signature as lowercase hex.
+///
+/// This matches how the server checks signatures.
+fn hmac_sign(key: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Returns the current UNIX timestamp in seconds.
+fn current_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time went backwards")
+        .as_secs() as i64
+}
+
+/// A request to verify a player's combo token.
+///
+/// This is sent by game servers to `/internal/combo/verify`.
+#[derive(Serialize, Deserialize)]
+pub struct ComboVerifyRequest {
+    /// The account's unique ID, as given by the client.
+    pub uid: i32,
+
+    /// The combo token given by the client.
+    pub combo_token: String,
+
+    /// The UNIX timestamp, in seconds, when the request was made.
+    pub timestamp: i64,
+
+    /// The HMAC-SHA256 signature of the other fields, keyed with the shared secret.
+    pub sign: String
+}
+
+impl ComboVerifyRequest {
+    /// Creates a signed request.
+    pub fn new(secret: &str, uid: i32, combo_token: &str) -> Self {
+        let mut request = ComboVerifyRequest {
+            uid,
+            combo_token: combo_token.to_string(),
+            timestamp: current_time(),
+            sign: String::new()
+        };
+        request.sign = hmac_sign(secret, &request.sign_message());
+
+        request
+    }
+
+    /// Returns the message which is signed.
+    ///
+    /// This contains every field except `sign`, sorted by name.
+    pub fn sign_message(&self) -> String {
+        format!(
+            "combo_token={}&timestamp={}&uid={}",
+            self.combo_token, self.timestamp, self.uid
+        )
+    }
+}
+
+/// The account behind a valid combo token.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerifiedAccount {
+    /// The account's unique ID.
+    pub uid: i32,
+
+    /// The account's state.
+    ///
+    /// This is `1` for active accounts.
+    pub state: i32,
+
+    /// The device the combo token was issued to.
+    pub device: String
+}
+
+/// The JSON envelope which wraps every SDK response.
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct Envelope {
+    retcode: i16,
+    message: String,
+    data: String
+}
+
+/// A client for the server-to-server API, used by game servers.
+#[cfg(feature = "http")]
+pub struct SdkClient {
+    http: reqwest::Client,
+    base_url: String,
+    secret: String
+}
+
+#[cfg(feature = "http")]
+impl SdkClient {
+    /// Creates a client for the SDK server at `base_url`.
+    ///
+    /// The `secret` must match `internal.secret` in the server's configuration.
+    pub fn new(base_url: impl Into<String>, secret: impl Into<String>) -> Self {
+        SdkClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            secret: secret.into()
+        }
+    }
+
+    /// Checks that the combo token is valid for the account.
+    ///
+    /// Returns an error with the server's message if the token was rejected.
+    pub async fn verify_combo_token(&self, uid: i32, combo_token: &str) -> anyhow::Result<VerifiedAccount> {
+        let request = ComboVerifyRequest::new(&self.secret, uid, combo_token);
+        let envelope: Envelope = self.http
+            .post(format!("{}/internal/combo/verify", self.base_url))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if envelope.retcode != 0 {
+            return Err(anyhow!("combo token rejected ({}): {}", envelope.retcode, envelope.message));
+        }
+
+        serde_json::from_str(&envelope.data).context("invalid response data")
+    }
+}
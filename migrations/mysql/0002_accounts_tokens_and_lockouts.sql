@@ -0,0 +1,94 @@
+-- Add guest logins, email verification and scheduled deletion to accounts.
+-- Timestamps are widened to 64 bits, so they don't overflow in 2038.
+ALTER TABLE `accounts`
+    ADD COLUMN `guest_device`   VARCHAR(512) UNIQUE AFTER `state`,
+    ADD COLUMN `email_verified` INTEGER NOT NULL DEFAULT 0 AFTER `guest_device`,
+    ADD COLUMN `auto_granted`   INTEGER NOT NULL DEFAULT 0 AFTER `email_verified`,
+    MODIFY COLUMN `epoch_created` BIGINT NOT NULL,
+    ADD COLUMN `epoch_delete`   BIGINT AFTER `epoch_created`;
+
+ALTER TABLE `devices`
+    MODIFY COLUMN `epoch_lastseen` BIGINT NOT NULL;
+
+-- Accounts which already have a device have used up their automatic grant.
+UPDATE `accounts` SET `auto_granted` = 1 WHERE `uid` IN (SELECT `uid` FROM `devices`);
+
+-- Expire login tokens.
+ALTER TABLE `login_tokens`
+    ADD COLUMN `epoch_created` BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN `epoch_expires` BIGINT NOT NULL DEFAULT 0;
+
+-- Expire tickets, and grant codes.
+-- Reactivation tickets are bound to the device they were issued to;
+-- tickets issued before this have no device, so they can't be used.
+ALTER TABLE `reactivate_tickets`
+    ADD COLUMN `device`        VARCHAR(512) NOT NULL DEFAULT '' AFTER `uid`,
+    ADD COLUMN `epoch_created` BIGINT NOT NULL DEFAULT 0;
+
+-- Grant tickets are keyed by the account and device together,
+-- so an account can have a ticket for each device, and a device for each account.
+ALTER TABLE `grant_tickets`
+    DROP INDEX `device`,
+    DROP INDEX `uid`,
+    MODIFY COLUMN `device`     VARCHAR(512) NOT NULL,
+    ADD COLUMN `attempts`      INTEGER NOT NULL DEFAULT 0 AFTER `code`,
+    ADD COLUMN `redeemed`      INTEGER NOT NULL DEFAULT 0 AFTER `attempts`,
+    ADD COLUMN `epoch_sent`    BIGINT AFTER `redeemed`,
+    ADD COLUMN `epoch_created` BIGINT NOT NULL DEFAULT 0 AFTER `epoch_sent`,
+    ADD INDEX (`ticket`);
+
+-- Initialize the mobile login codes table.
+CREATE TABLE `mobile_captchas` (
+                            `mobile`     VARCHAR(16) NOT NULL PRIMARY KEY,
+                            `code`       VARCHAR(6) NOT NULL,
+                            `attempts`   INTEGER NOT NULL DEFAULT 0,
+                            `epoch_sent` BIGINT NOT NULL
+);
+
+-- Initialize the email verification table.
+CREATE TABLE `email_verifications` (
+                            `uid`           INTEGER NOT NULL PRIMARY KEY,
+                            `token`         VARCHAR(32) NOT NULL,
+                            `epoch_created` BIGINT NOT NULL
+);
+
+-- Initialize the login lockout tables.
+CREATE TABLE `login_failures` (
+                            `subject`      VARCHAR(128) NOT NULL,
+                            `epoch_failed` BIGINT NOT NULL,
+                            INDEX (`subject`, `epoch_failed`)
+);
+
+CREATE TABLE `login_lockouts` (
+                            `subject`     VARCHAR(128) NOT NULL PRIMARY KEY,
+                            `epoch_until` BIGINT NOT NULL
+);
+
+-- Initialize the account state history table.
+CREATE TABLE `account_state_history` (
+                            `id`            INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+                            `uid`           INTEGER NOT NULL,
+                            `from_state`    INTEGER NOT NULL,
+                            `to_state`      INTEGER NOT NULL,
+                            `reason`        VARCHAR(128) NOT NULL,
+                            `epoch_changed` BIGINT NOT NULL,
+                            INDEX (`uid`)
+);
+
+-- Initialize the combo tokens table.
+CREATE TABLE `combo_tokens` (
+                            `combo_id`      INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+                            `uid`           INTEGER NOT NULL,
+                            `device`        VARCHAR(512) NOT NULL,
+                            `token`         VARCHAR(64) NOT NULL,
+                            `epoch_created` BIGINT NOT NULL,
+                            `epoch_expires` BIGINT NOT NULL,
+                            UNIQUE (`uid`, `device`)
+);
+
+-- Initialize the password resets table.
+CREATE TABLE `password_resets` (
+                            `uid`           INTEGER NOT NULL PRIMARY KEY,
+                            `token_hash`    VARCHAR(64) NOT NULL,
+                            `epoch_created` BIGINT NOT NULL
+);
@@ -0,0 +1,123 @@
+-- The full schema, as of version 2 of the MySQL migrations.
+-- This is numbered to match, so each version is the same schema on every backend.
+
+-- Initialize the accounts table.
+CREATE TABLE IF NOT EXISTS accounts (
+                            uid            SERIAL PRIMARY KEY,
+                            name           VARCHAR(64) UNIQUE,
+                            email          VARCHAR(128) UNIQUE,
+                            mobile         VARCHAR(16) UNIQUE,
+                            password       TEXT,
+                            state          INTEGER NOT NULL DEFAULT 1,
+                            guest_device   VARCHAR(512) UNIQUE,
+                            email_verified INTEGER NOT NULL DEFAULT 0,
+                            auto_granted   INTEGER NOT NULL DEFAULT 0,
+                            epoch_created  BIGINT NOT NULL,
+                            epoch_delete   BIGINT
+);
+
+-- Initialize the devices table.
+CREATE TABLE IF NOT EXISTS devices (
+                            uid            INTEGER NOT NULL,
+                            device         VARCHAR(512) NOT NULL,
+                            epoch_lastseen BIGINT NOT NULL,
+                            PRIMARY KEY (uid, device)
+);
+
+-- Initialize the data tables.
+CREATE TABLE IF NOT EXISTS login_tokens (
+                            uid           INTEGER NOT NULL,
+                            token         TEXT NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            epoch_created BIGINT NOT NULL DEFAULT 0,
+                            epoch_expires BIGINT NOT NULL DEFAULT 0,
+                            PRIMARY KEY (uid, device)
+);
+
+-- This table is not used in `pancake`.
+CREATE TABLE IF NOT EXISTS realnames (
+                            uid           INTEGER NOT NULL PRIMARY KEY,
+                            name          TEXT NOT NULL,
+                            identity      VARCHAR(128) NOT NULL UNIQUE,
+                            is_realperson INTEGER NOT NULL DEFAULT 0
+);
+
+-- Initialize the tickets tables.
+CREATE TABLE IF NOT EXISTS reactivate_tickets (
+                            ticket        VARCHAR(32) NOT NULL PRIMARY KEY,
+                            uid           INTEGER NOT NULL UNIQUE,
+                            device        VARCHAR(512) NOT NULL,
+                            epoch_created BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS grant_tickets (
+                            ticket        VARCHAR(32) NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            uid           INTEGER NOT NULL,
+                            code          TEXT,
+                            attempts      INTEGER NOT NULL DEFAULT 0,
+                            redeemed      INTEGER NOT NULL DEFAULT 0,
+                            epoch_sent    BIGINT,
+                            epoch_created BIGINT NOT NULL DEFAULT 0,
+                            PRIMARY KEY (uid, device)
+);
+
+CREATE INDEX IF NOT EXISTS grant_tickets_ticket ON grant_tickets (ticket);
+
+-- Initialize the mobile login codes table.
+CREATE TABLE IF NOT EXISTS mobile_captchas (
+                            mobile     VARCHAR(16) NOT NULL PRIMARY KEY,
+                            code       VARCHAR(6) NOT NULL,
+                            attempts   INTEGER NOT NULL DEFAULT 0,
+                            epoch_sent BIGINT NOT NULL
+);
+
+-- Initialize the email verification table.
+CREATE TABLE IF NOT EXISTS email_verifications (
+                            uid           INTEGER NOT NULL PRIMARY KEY,
+                            token         VARCHAR(32) NOT NULL,
+                            epoch_created BIGINT NOT NULL
+);
+
+-- Initialize the login lockout tables.
+CREATE TABLE IF NOT EXISTS login_failures (
+                            subject      VARCHAR(128) NOT NULL,
+                            epoch_failed BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS login_failures_subject ON login_failures (subject, epoch_failed);
+
+CREATE TABLE IF NOT EXISTS login_lockouts (
+                            subject     VARCHAR(128) NOT NULL PRIMARY KEY,
+                            epoch_until BIGINT NOT NULL
+);
+
+-- Initialize the account state history table.
+CREATE TABLE IF NOT EXISTS account_state_history (
+                            id            SERIAL PRIMARY KEY,
+                            uid           INTEGER NOT NULL,
+                            from_state    INTEGER NOT NULL,
+                            to_state      INTEGER NOT NULL,
+                            reason        VARCHAR(128) NOT NULL,
+                            epoch_changed BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS account_state_history_uid ON account_state_history (uid);
+
+-- Initialize the combo tokens table.
+CREATE TABLE IF NOT EXISTS combo_tokens (
+                            combo_id      SERIAL PRIMARY KEY,
+                            uid           INTEGER NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            token         VARCHAR(64) NOT NULL,
+                            epoch_created BIGINT NOT NULL,
+                            epoch_expires BIGINT NOT NULL,
+                            UNIQUE (uid, device)
+);
+
+-- Initialize the password resets table.
+CREATE TABLE IF NOT EXISTS password_resets (
+                            uid           INTEGER NOT NULL PRIMARY KEY,
+                            token_hash    VARCHAR(64) NOT NULL,
+                            epoch_created BIGINT NOT NULL
+);
@@ -0,0 +1,127 @@
+-- The full schema, as of version 2 of the MySQL migrations.
+-- This is numbered to match, so each version is the same schema on every backend.
+--
+-- Columns are declared as `INT4` so they decode as `i32`, like MySQL's `INTEGER`,
+-- and timestamps as `BIGINT` so they decode as `i64`.
+-- Row IDs must be declared as `INTEGER`, so queries cast them instead.
+
+-- Initialize the accounts table.
+CREATE TABLE IF NOT EXISTS accounts (
+                            uid            INTEGER PRIMARY KEY AUTOINCREMENT,
+                            name           VARCHAR(64) UNIQUE,
+                            email          VARCHAR(128) UNIQUE,
+                            mobile         VARCHAR(16) UNIQUE,
+                            password       TEXT,
+                            state          INT4 NOT NULL DEFAULT 1,
+                            guest_device   VARCHAR(512) UNIQUE,
+                            email_verified INT4 NOT NULL DEFAULT 0,
+                            auto_granted   INT4 NOT NULL DEFAULT 0,
+                            epoch_created  BIGINT NOT NULL,
+                            epoch_delete   BIGINT
+);
+
+-- Initialize the devices table.
+CREATE TABLE IF NOT EXISTS devices (
+                            uid            INT4 NOT NULL,
+                            device         VARCHAR(512) NOT NULL,
+                            epoch_lastseen BIGINT NOT NULL,
+                            PRIMARY KEY (uid, device)
+);
+
+-- Initialize the data tables.
+CREATE TABLE IF NOT EXISTS login_tokens (
+                            uid           INT4 NOT NULL,
+                            token         TEXT NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            epoch_created BIGINT NOT NULL DEFAULT 0,
+                            epoch_expires BIGINT NOT NULL DEFAULT 0,
+                            PRIMARY KEY (uid, device)
+);
+
+-- This table is not used in `pancake`.
+CREATE TABLE IF NOT EXISTS realnames (
+                            uid           INT4 NOT NULL PRIMARY KEY,
+                            name          TEXT NOT NULL,
+                            identity      VARCHAR(128) NOT NULL UNIQUE,
+                            is_realperson INT4 NOT NULL DEFAULT 0
+);
+
+-- Initialize the tickets tables.
+CREATE TABLE IF NOT EXISTS reactivate_tickets (
+                            ticket        VARCHAR(32) NOT NULL PRIMARY KEY,
+                            uid           INT4 NOT NULL UNIQUE,
+                            device        VARCHAR(512) NOT NULL,
+                            epoch_created BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS grant_tickets (
+                            ticket        VARCHAR(32) NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            uid           INT4 NOT NULL,
+                            code          TEXT,
+                            attempts      INT4 NOT NULL DEFAULT 0,
+                            redeemed      INT4 NOT NULL DEFAULT 0,
+                            epoch_sent    BIGINT,
+                            epoch_created BIGINT NOT NULL DEFAULT 0,
+                            PRIMARY KEY (uid, device)
+);
+
+CREATE INDEX IF NOT EXISTS grant_tickets_ticket ON grant_tickets (ticket);
+
+-- Initialize the mobile login codes table.
+CREATE TABLE IF NOT EXISTS mobile_captchas (
+                            mobile     VARCHAR(16) NOT NULL PRIMARY KEY,
+                            code       VARCHAR(6) NOT NULL,
+                            attempts   INT4 NOT NULL DEFAULT 0,
+                            epoch_sent BIGINT NOT NULL
+);
+
+-- Initialize the email verification table.
+CREATE TABLE IF NOT EXISTS email_verifications (
+                            uid           INT4 NOT NULL PRIMARY KEY,
+                            token         VARCHAR(32) NOT NULL,
+                            epoch_created BIGINT NOT NULL
+);
+
+-- Initialize the login lockout tables.
+CREATE TABLE IF NOT EXISTS login_failures (
+                            subject      VARCHAR(128) NOT NULL,
+                            epoch_failed BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS login_failures_subject ON login_failures (subject, epoch_failed);
+
+CREATE TABLE IF NOT EXISTS login_lockouts (
+                            subject     VARCHAR(128) NOT NULL PRIMARY KEY,
+                            epoch_until BIGINT NOT NULL
+);
+
+-- Initialize the account state history table.
+CREATE TABLE IF NOT EXISTS account_state_history (
+                            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                            uid           INT4 NOT NULL,
+                            from_state    INT4 NOT NULL,
+                            to_state      INT4 NOT NULL,
+                            reason        VARCHAR(128) NOT NULL,
+                            epoch_changed BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS account_state_history_uid ON account_state_history (uid);
+
+-- Initialize the combo tokens table.
+CREATE TABLE IF NOT EXISTS combo_tokens (
+                            combo_id      INTEGER PRIMARY KEY AUTOINCREMENT,
+                            uid           INT4 NOT NULL,
+                            device        VARCHAR(512) NOT NULL,
+                            token         VARCHAR(64) NOT NULL,
+                            epoch_created BIGINT NOT NULL,
+                            epoch_expires BIGINT NOT NULL,
+                            UNIQUE (uid, device)
+);
+
+-- Initialize the password resets table.
+CREATE TABLE IF NOT EXISTS password_resets (
+                            uid           INT4 NOT NULL PRIMARY KEY,
+                            token_hash    VARCHAR(64) NOT NULL,
+                            epoch_created BIGINT NOT NULL
+);
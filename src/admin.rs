@@ -0,0 +1,461 @@
+use std::net::IpAddr;
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use sqlx::Connection;
+use validator::Validate;
+
+use crate::{config::Config, lockout::{self, Subject}, migrate, purge, state, utils};
+use crate::db::{query, DbConnection};
+
+pub use crate::error::AdminError;
+pub use crate::state::AccountState;
+
+/// The most accounts returned by a search.
+const SEARCH_LIMIT: i64 = 50;
+
+/// How many state changes are shown with an account.
+const HISTORY_LIMIT: i64 = 20;
+
+/// Server settings needed to manage accounts outside of the server.
+pub struct Settings {
+    /// How long, in seconds, an account can be reactivated after deletion is requested.
+    pub deletion_grace: u32
+}
+
+/// A short description of an account, as returned by a search.
+#[derive(Serialize)]
+pub struct AccountSummary {
+    pub uid: i32,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub mobile: Option<String>,
+
+    /// The account's state, as stored in the database.
+    ///
+    /// See `AccountState` for the values.
+    pub state: i32
+}
+
+/// A change to an account's state, from its history.
+#[derive(Serialize)]
+pub struct StateChange {
+    pub from_state: i32,
+    pub to_state: i32,
+    pub reason: String,
+    pub epoch_changed: i64
+}
+
+/// The full details of an account.
+#[derive(Serialize)]
+pub struct AccountDetails {
+    #[serde(flatten)]
+    pub summary: AccountSummary,
+
+    pub email_verified: bool,
+    pub guest: bool,
+    pub epoch_created: i64,
+
+    /// When the account will be purged, if it's pending deletion.
+    pub epoch_delete: Option<i64>,
+
+    /// Whether the account is locked out after failed logins.
+    pub locked: bool,
+
+    /// The most recent state changes, newest first.
+    pub history: Vec<StateChange>
+}
+
+/// A device which can log in to an account without a grant.
+#[derive(Serialize)]
+pub struct DeviceInfo {
+    pub device: String,
+    pub epoch_lastseen: i64
+}
+
+/// A login token, without the token itself.
+#[derive(Serialize)]
+pub struct TokenInfo {
+    pub device: String,
+    pub epoch_created: i64,
+    pub epoch_expires: i64
+}
+
+/// The details of a new account.
+#[derive(Validate)]
+pub struct NewAccount<'a> {
+    #[validate(length(min = 2, max = 64))]
+    pub name: &'a str,
+
+    #[validate(email, length(max = 128))]
+    pub email: &'a str,
+
+    #[validate(length(min = 8, max = 128))]
+    pub password: &'a str
+}
+
+#[derive(Validate)]
+struct NewPassword<'a> {
+    #[validate(length(min = 8, max = 128))]
+    password: &'a str
+}
+
+/// Connects to the database configured in `Rocket.toml`, without starting the server.
+///
+/// Like the server, this reads the `ROCKET_PROFILE` and `ROCKET_*` environment variables.
+/// Fails if the database schema doesn't match this build.
+pub async fn connect() -> anyhow::Result<(DbConnection, Settings)> {
+    let figment = rocket::Config::figment();
+    let url: String = figment.extract_inner("databases.sdk.url")
+        .context("The database URL isn't set in `databases.sdk.url`")?;
+    let config: Config = figment.extract().unwrap_or_default();
+
+    let mut db = DbConnection::connect(&url).await
+        .context("Failed to connect to the database")?;
+
+    // Migrations are only applied by the server.
+    let pending = migrate::check_schema(&mut db).await?;
+    if pending > 0 {
+        bail!("{} database migration(s) are pending; start the server to apply them", pending);
+    }
+
+    Ok((db, Settings { deletion_grace: config.accounts.deletion_grace }))
+}
+
+/// Escapes the wildcards in a `LIKE` pattern, and matches anything which starts with it.
+fn like_prefix(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
+/// Fails with `AdminError::NotFound` if the account doesn't exist.
+async fn ensure_account(db: &mut DbConnection, uid: i32) -> Result<(), AdminError> {
+    query!(
+        "SELECT `uid` FROM `accounts` WHERE `uid` = ?",
+        "SELECT uid FROM accounts WHERE uid = $1",
+        uid
+    ).fetch_optional(&mut *db).await?.ok_or(AdminError::NotFound)?;
+
+    Ok(())
+}
+
+/// Searches for accounts by their ID, or the start of their username or email.
+///
+/// Every given filter must match.
+pub async fn search_accounts(
+    db: &mut DbConnection,
+    uid: Option<i32>,
+    name: Option<&str>,
+    email: Option<&str>
+) -> Result<Vec<AccountSummary>, AdminError> {
+    if uid.is_none() && name.is_none() && email.is_none() {
+        return Err(AdminError::BadRequest("Provide at least one of 'uid', 'name' or 'email'."));
+    }
+
+    let name = name.map(like_prefix);
+    let email = email.map(like_prefix);
+    let accounts = query!(
+        "SELECT `uid`, `name`, `email`, `mobile`, `state` FROM `accounts` WHERE (? IS NULL OR `uid` = ?) AND (? IS NULL OR `name` LIKE ?) AND (? IS NULL OR `email` LIKE ?) ORDER BY `uid` LIMIT ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, state FROM accounts WHERE (CAST($1 AS INTEGER) IS NULL OR uid = $2) AND (CAST($3 AS TEXT) IS NULL OR name LIKE $4 ESCAPE '\') AND (CAST($5 AS TEXT) IS NULL OR email LIKE $6 ESCAPE '\') ORDER BY uid LIMIT $7"#,
+        uid, uid, name, name, email, email, SEARCH_LIMIT
+    ).fetch_all(&mut *db).await?;
+
+    Ok(accounts.into_iter()
+        .map(|account| AccountSummary {
+            uid: account.uid,
+            name: account.name,
+            email: account.email,
+            mobile: account.mobile,
+            state: account.state
+        })
+        .collect())
+}
+
+/// Returns an account's details, including its recent state changes.
+pub async fn get_account(db: &mut DbConnection, uid: i32) -> Result<AccountDetails, AdminError> {
+    let account = query!(
+        "SELECT * FROM `accounts` WHERE `uid` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, state, guest_device, email_verified, epoch_created, epoch_delete FROM accounts WHERE uid = $1"#,
+        uid
+    ).fetch_optional(&mut *db).await?.ok_or(AdminError::NotFound)?;
+
+    let history = query!(
+        "SELECT `from_state`, `to_state`, `reason`, `epoch_changed` FROM `account_state_history` WHERE `uid` = ? ORDER BY `id` DESC LIMIT ?",
+        "SELECT from_state, to_state, reason, epoch_changed FROM account_state_history WHERE uid = $1 ORDER BY id DESC LIMIT $2",
+        uid, HISTORY_LIMIT
+    ).fetch_all(&mut *db).await?;
+
+    let locked = lockout::is_locked(&mut *db, &[Subject::Account(uid)]).await?;
+
+    Ok(AccountDetails {
+        summary: AccountSummary {
+            uid: account.uid,
+            name: account.name,
+            email: account.email,
+            mobile: account.mobile,
+            state: account.state
+        },
+        email_verified: account.email_verified != 0,
+        guest: account.guest_device.is_some(),
+        epoch_created: account.epoch_created,
+        epoch_delete: account.epoch_delete,
+        locked,
+        history: history.into_iter()
+            .map(|change| StateChange {
+                from_state: change.from_state,
+                to_state: change.to_state,
+                reason: change.reason,
+                epoch_changed: change.epoch_changed
+            })
+            .collect()
+    })
+}
+
+/// Creates an account with a username, email and password.
+///
+/// The email is marked as verified, since an operator created the account.
+/// Returns the new account's unique ID.
+pub async fn create_account(db: &mut DbConnection, account: NewAccount<'_>) -> Result<i32, AdminError> {
+    if account.validate().is_err() {
+        return Err(AdminError::BadRequest("The username, email or password is invalid."));
+    }
+
+    let existing = query!(
+        "SELECT `uid` FROM `accounts` WHERE `name` = ? OR `email` = ?",
+        "SELECT uid FROM accounts WHERE name = $1 OR email = $2",
+        account.name, account.email
+    ).fetch_optional(&mut *db).await?;
+    if existing.is_some() {
+        return Err(AdminError::Conflict("An account with the username or email already exists.".to_string()));
+    }
+
+    let hashed = utils::hash_password(account.password).map_err(|_| AdminError::System)?;
+    let current_time = utils::current_time();
+
+    #[cfg(feature = "mysql")]
+    let uid = sqlx::query!(
+        "INSERT INTO `accounts` (`name`, `email`, `password`, `email_verified`, `epoch_created`) VALUES (?, ?, ?, 1, ?)",
+        account.name, account.email, hashed, current_time
+    ).execute(&mut *db).await?.last_insert_id() as i32;
+
+    #[cfg(not(feature = "mysql"))]
+    let uid = sqlx::query_scalar!(
+        r#"INSERT INTO accounts (name, email, password, email_verified, epoch_created) VALUES ($1, $2, $3, 1, $4) RETURNING uid AS "uid!: i32""#,
+        account.name, account.email, hashed, current_time
+    ).fetch_one(&mut *db).await?;
+
+    Ok(uid)
+}
+
+/// Moves an account to a new state.
+///
+/// Moving an account to `PendingDelete` schedules it for deletion after `deletion_grace` seconds,
+/// and moving it to `Deleted` purges it immediately.
+pub async fn set_state(
+    db: &mut DbConnection,
+    uid: i32,
+    to: AccountState,
+    reason: &str,
+    deletion_grace: u32
+) -> Result<AccountSummary, AdminError> {
+    if reason.trim().is_empty() {
+        return Err(AdminError::BadRequest("A reason is required."));
+    }
+
+    // The history only has room for 128 characters.
+    let reason: String = reason.trim().chars().take(128).collect();
+
+    if to == AccountState::Deleted {
+        purge::purge_account(&mut *db, uid, &reason).await?;
+    } else {
+        let mut tx = db.begin().await?;
+        state::transition(&mut tx, uid, to, &reason).await?;
+
+        // Only accounts pending deletion have a deletion date.
+        let deletion_date = (to == AccountState::PendingDelete)
+            .then(|| utils::current_time() + i64::from(deletion_grace));
+        query!(
+            "UPDATE `accounts` SET `epoch_delete` = ? WHERE `uid` = ?",
+            "UPDATE accounts SET epoch_delete = $1 WHERE uid = $2",
+            deletion_date, uid
+        ).execute(&mut *tx).await?;
+        tx.commit().await?;
+    }
+
+    let account = query!(
+        "SELECT `uid`, `name`, `email`, `mobile`, `state` FROM `accounts` WHERE `uid` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, state FROM accounts WHERE uid = $1"#,
+        uid
+    ).fetch_one(&mut *db).await?;
+
+    Ok(AccountSummary {
+        uid: account.uid,
+        name: account.name,
+        email: account.email,
+        mobile: account.mobile,
+        state: account.state
+    })
+}
+
+/// Sets an account's password.
+///
+/// Every login and combo token is revoked, so the account must log in again.
+/// Returns how many tokens were revoked.
+pub async fn set_password(db: &mut DbConnection, uid: i32, password: &str) -> Result<u64, AdminError> {
+    if (NewPassword { password }).validate().is_err() {
+        return Err(AdminError::BadRequest("Passwords must be between 8 and 128 characters."));
+    }
+
+    let account = query!(
+        "SELECT `state` FROM `accounts` WHERE `uid` = ?",
+        "SELECT state FROM accounts WHERE uid = $1",
+        uid
+    ).fetch_optional(&mut *db).await?.ok_or(AdminError::NotFound)?;
+
+    // Frozen and deleted accounts can't be changed.
+    if account.state == AccountState::LegalHold || account.state == AccountState::Deleted {
+        return Err(AdminError::Conflict("The account's state doesn't allow changes.".to_string()));
+    }
+
+    let hashed = utils::hash_password(password).map_err(|_| AdminError::System)?;
+
+    let mut tx = db.begin().await?;
+    query!(
+        "UPDATE `accounts` SET `password` = ? WHERE `uid` = ?",
+        "UPDATE accounts SET password = $1 WHERE uid = $2",
+        hashed, uid
+    ).execute(&mut *tx).await?;
+
+    // Any outstanding reset link would undo the change.
+    query!(
+        "DELETE FROM `password_resets` WHERE `uid` = ?",
+        "DELETE FROM password_resets WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    let revoked = revoke_tokens(&mut tx, uid, None).await?;
+    tx.commit().await?;
+
+    Ok(revoked)
+}
+
+/// Clears an account's failed logins, and unlocks it.
+pub async fn clear_lockout(db: &mut DbConnection, uid: i32) -> Result<(), AdminError> {
+    ensure_account(&mut *db, uid).await?;
+    lockout::clear(&mut *db, Subject::Account(uid)).await?;
+
+    Ok(())
+}
+
+/// Clears an IP address's failed logins, sent codes and created guest accounts, and unlocks it.
+pub async fn clear_address_lockout(db: &mut DbConnection, address: &str) -> Result<(), AdminError> {
+    // Addresses are recorded in their canonical form.
+    let address: IpAddr = address.trim().parse()
+        .map_err(|_| AdminError::BadRequest("The IP address is invalid."))?;
+    let address = address.to_string();
+    lockout::clear(&mut *db, Subject::Address(&address)).await?;
+    lockout::clear(&mut *db, Subject::SmsAddress(&address)).await?;
+    lockout::clear(&mut *db, Subject::GuestAddress(&address)).await?;
+
+    Ok(())
+}
+
+/// Lists the devices which can log in to an account without a grant.
+pub async fn list_devices(db: &mut DbConnection, uid: i32) -> Result<Vec<DeviceInfo>, AdminError> {
+    ensure_account(&mut *db, uid).await?;
+
+    let devices = query!(
+        "SELECT `device`, `epoch_lastseen` FROM `devices` WHERE `uid` = ? ORDER BY `epoch_lastseen` DESC",
+        "SELECT device, epoch_lastseen FROM devices WHERE uid = $1 ORDER BY epoch_lastseen DESC",
+        uid
+    ).fetch_all(&mut *db).await?;
+
+    Ok(devices.into_iter()
+        .map(|device| DeviceInfo {
+            device: device.device,
+            epoch_lastseen: device.epoch_lastseen
+        })
+        .collect())
+}
+
+/// Removes a device from an account, along with its tokens.
+///
+/// The device needs a new grant the next time it logs in.
+pub async fn revoke_device(db: &mut DbConnection, uid: i32, device: &str) -> Result<(), AdminError> {
+    let mut tx = db.begin().await?;
+    let removed = query!(
+        "DELETE FROM `devices` WHERE `uid` = ? AND `device` = ?",
+        "DELETE FROM devices WHERE uid = $1 AND device = $2",
+        uid, device
+    ).execute(&mut *tx).await?.rows_affected();
+    if removed == 0 {
+        return Err(AdminError::NotFound);
+    }
+
+    revoke_tokens(&mut tx, uid, Some(device)).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Lists an account's login tokens.
+///
+/// The tokens themselves are never returned.
+pub async fn list_tokens(db: &mut DbConnection, uid: i32) -> Result<Vec<TokenInfo>, AdminError> {
+    ensure_account(&mut *db, uid).await?;
+
+    let tokens = query!(
+        "SELECT `device`, `epoch_created`, `epoch_expires` FROM `login_tokens` WHERE `uid` = ? ORDER BY `epoch_created` DESC",
+        "SELECT device, epoch_created, epoch_expires FROM login_tokens WHERE uid = $1 ORDER BY epoch_created DESC",
+        uid
+    ).fetch_all(&mut *db).await?;
+
+    Ok(tokens.into_iter()
+        .map(|token| TokenInfo {
+            device: token.device,
+            epoch_created: token.epoch_created,
+            epoch_expires: token.epoch_expires
+        })
+        .collect())
+}
+
+/// Revokes the login and combo tokens of an account, or only those issued to one device.
+///
+/// Returns how many tokens were revoked.
+pub async fn revoke_tokens(db: &mut DbConnection, uid: i32, device: Option<&str>) -> Result<u64, AdminError> {
+    ensure_account(&mut *db, uid).await?;
+
+    let mut tx = db.begin().await?;
+    let login = query!(
+        "DELETE FROM `login_tokens` WHERE `uid` = ? AND (? IS NULL OR `device` = ?)",
+        "DELETE FROM login_tokens WHERE uid = $1 AND (CAST($2 AS TEXT) IS NULL OR device = $3)",
+        uid, device, device
+    ).execute(&mut *tx).await?.rows_affected();
+    let combo = query!(
+        "DELETE FROM `combo_tokens` WHERE `uid` = ? AND (? IS NULL OR `device` = ?)",
+        "DELETE FROM combo_tokens WHERE uid = $1 AND (CAST($2 AS TEXT) IS NULL OR device = $3)",
+        uid, device, device
+    ).execute(&mut *tx).await?.rows_affected();
+    tx.commit().await?;
+
+    Ok(login + combo)
+}
+
+/// Removes every pending grant ticket of an account.
+///
+/// Devices waiting for a grant must log in again to get a new ticket.
+/// Returns how many tickets were removed.
+pub async fn clear_tickets(db: &mut DbConnection, uid: i32) -> Result<u64, AdminError> {
+    ensure_account(&mut *db, uid).await?;
+
+    let removed = query!(
+        "DELETE FROM `grant_tickets` WHERE `uid` = ?",
+        "DELETE FROM grant_tickets WHERE uid = $1",
+        uid
+    ).execute(&mut *db).await?.rows_affected();
+
+    Ok(removed)
+}
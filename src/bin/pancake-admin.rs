@@ -0,0 +1,192 @@
+use std::io::Write;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use pancake::admin::{self, AccountState, NewAccount};
+
+/// Manages accounts in the SDK database, without starting the server.
+///
+/// The database is read from `Rocket.toml`, like the server.
+#[derive(Parser)]
+#[command(name = "pancake-admin", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates an account, and prints its unique ID.
+    ///
+    /// The password is read from `PANCAKE_ADMIN_PASSWORD`, or prompted for.
+    Create {
+        name: String,
+        email: String
+    },
+
+    /// Prints an account's details.
+    Show {
+        uid: i32
+    },
+
+    /// Searches for accounts by their ID, or the start of their username or email.
+    Search {
+        #[arg(long)]
+        uid: Option<i32>,
+
+        #[arg(long)]
+        name: Option<String>,
+
+        #[arg(long)]
+        email: Option<String>
+    },
+
+    /// Sets an account's password, and revokes its tokens.
+    ///
+    /// The password is read from `PANCAKE_ADMIN_PASSWORD`, or prompted for.
+    Password {
+        uid: i32
+    },
+
+    /// Moves an account to a new state.
+    State {
+        uid: i32,
+
+        #[arg(value_enum)]
+        state: State,
+
+        /// Why the state is being changed, recorded in the account's state history.
+        #[arg(long)]
+        reason: String
+    },
+
+    /// Lists the devices which can log in to an account without a grant.
+    Devices {
+        uid: i32
+    },
+
+    /// Revokes an account's login and combo tokens.
+    Revoke {
+        uid: i32,
+
+        /// Only revoke the tokens issued to this device.
+        #[arg(long)]
+        device: Option<String>
+    },
+
+    /// Clears an account's failed logins, and unlocks it.
+    Unlock {
+        uid: i32
+    },
+
+    /// Clears an IP address's failed logins and sent codes, and unlocks it.
+    UnlockAddress {
+        address: String
+    }
+}
+
+/// The states an account can be moved to.
+///
+/// See `AccountState` for what each one means.
+#[derive(Clone, Copy, ValueEnum)]
+enum State {
+    Active,
+    PendingDelete,
+    LegalHold,
+    Deleted
+}
+
+impl From<State> for AccountState {
+    fn from(state: State) -> Self {
+        match state {
+            State::Active => AccountState::Active,
+            State::PendingDelete => AccountState::PendingDelete,
+            State::LegalHold => AccountState::LegalHold,
+            State::Deleted => AccountState::Deleted
+        }
+    }
+}
+
+/// The environment variable which a password is read from, instead of prompting for it.
+const PASSWORD_ENV: &str = "PANCAKE_ADMIN_PASSWORD";
+
+/// Reads a password from `PASSWORD_ENV`, or prompts for one on standard input.
+///
+/// Passwords aren't taken as arguments, so they don't end up in shell history or the process list.
+fn read_password() -> anyhow::Result<String> {
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+
+    eprint!("Password: ");
+    std::io::stderr().flush()?;
+
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        anyhow::bail!("A password is required.");
+    }
+
+    Ok(password.to_string())
+}
+
+/// Prints a value as JSON, so the output can be read by scripts.
+fn print(value: impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+#[rocket::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let (mut db, settings) = admin::connect().await?;
+
+    match cli.command {
+        Command::Create { name, email } => {
+            let password = read_password()?;
+            let account = NewAccount {
+                name: &name,
+                email: &email,
+                password: &password
+            };
+            println!("{}", admin::create_account(&mut db, account).await?);
+        },
+        Command::Show { uid } => {
+            print(admin::get_account(&mut db, uid).await?)?;
+        },
+        Command::Search { uid, name, email } => {
+            print(admin::search_accounts(&mut db, uid, name.as_deref(), email.as_deref()).await?)?;
+        },
+        Command::Password { uid } => {
+            let password = read_password()?;
+            let revoked = admin::set_password(&mut db, uid, &password).await?;
+            eprintln!("Revoked {} token(s).", revoked);
+        },
+        Command::State { uid, state, reason } => {
+            if reason.trim().is_empty() {
+                anyhow::bail!("A reason is required.");
+            }
+
+            // Record that the change was made from here in the account's history.
+            let reason = format!("{} (by pancake-admin)", reason.trim());
+            print(admin::set_state(&mut db, uid, state.into(), &reason, settings.deletion_grace).await?)?;
+        },
+        Command::Devices { uid } => {
+            print(admin::list_devices(&mut db, uid).await?)?;
+        },
+        Command::Revoke { uid, device } => {
+            let revoked = admin::revoke_tokens(&mut db, uid, device.as_deref()).await?;
+            eprintln!("Revoked {} token(s).", revoked);
+        },
+        Command::Unlock { uid } => {
+            admin::clear_lockout(&mut db, uid).await?;
+        },
+        Command::UnlockAddress { address } => {
+            admin::clear_address_lockout(&mut db, &address).await?;
+        }
+    }
+
+    Ok(())
+}
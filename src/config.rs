@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde::Deserialize;
+
+/// Server configuration, read from `Rocket.toml`.
+///
+/// Every section is optional, and falls back to its defaults.
+#[derive(Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub tokens: TokenConfig,
+
+    #[serde(default)]
+    pub sms: SmsConfig,
+
+    #[serde(default)]
+    pub mail: MailConfig,
+
+    #[serde(default)]
+    pub accounts: AccountConfig,
+
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    #[serde(default)]
+    pub rsa: RsaConfig,
+
+    #[serde(default)]
+    pub combo: ComboConfig,
+
+    #[serde(default)]
+    pub internal: InternalConfig,
+
+    #[serde(default)]
+    pub client: ClientConfig,
+
+    #[serde(default)]
+    pub stubs: StubConfig,
+
+    #[serde(default)]
+    pub dispatch: DispatchConfig,
+
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    #[serde(default)]
+    pub migrations: MigrationConfig
+}
+
+/// Configuration for login tokens.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TokenConfig {
+    /// How long, in seconds, a login token is valid for.
+    pub lifetime: u32,
+
+    /// Overrides for `lifetime`, keyed by the client type.
+    ///
+    /// See the `ClientType` guard for the values.
+    pub client_lifetimes: HashMap<String, u32>
+}
+
+impl TokenConfig {
+    /// Returns the token lifetime for the given client type.
+    pub fn lifetime_for(&self, client_type: u8) -> u32 {
+        self.client_lifetimes
+            .get(&client_type.to_string())
+            .copied()
+            .unwrap_or(self.lifetime)
+    }
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        TokenConfig {
+            // 30 days.
+            lifetime: 60 * 60 * 24 * 30,
+            client_lifetimes: HashMap::new()
+        }
+    }
+}
+
+
+/// The kinds of SMS senders which can be configured.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsSenderKind {
+    /// Writes messages to the log.
+    #[default]
+    Log,
+
+    /// Appends messages to the file at `path`.
+    File
+}
+
+/// Configuration for sending text messages.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SmsConfig {
+    /// Which sender should be used.
+    pub sender: SmsSenderKind,
+
+    /// The file written to by the `file` sender.
+    pub path: String
+}
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        SmsConfig {
+            sender: SmsSenderKind::Log,
+            path: "sms.log".to_string()
+        }
+    }
+}
+
+/// The kinds of mail transports which can be configured.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransportKind {
+    /// Writes emails to the log.
+    #[default]
+    Log,
+
+    /// Appends emails to the file at `path`.
+    File,
+
+    /// Sends emails through the configured SMTP server.
+    Smtp
+}
+
+/// Configuration for sending emails.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    /// Which transport should be used.
+    pub transport: MailTransportKind,
+
+    /// The file written to by the `file` transport.
+    pub path: String,
+
+    /// The sender of every email.
+    pub from: String,
+
+    /// The public address of the server.
+    ///
+    /// This is used to build links in emails.
+    pub base_url: String,
+
+    /// The host name of the SMTP server.
+    pub smtp_host: String,
+
+    /// The port of the SMTP server.
+    pub smtp_port: u16,
+
+    /// Whether the connection to the SMTP server should use STARTTLS.
+    pub smtp_tls: bool,
+
+    /// The username used to authenticate with the SMTP server.
+    pub smtp_username: Option<String>,
+
+    /// The password used to authenticate with the SMTP server.
+    pub smtp_password: Option<String>
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            transport: MailTransportKind::Log,
+            path: "mail.log".to_string(),
+            from: "pancake <noreply@localhost>".to_string(),
+            base_url: "http://127.0.0.1:8000".to_string(),
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: 25,
+            smtp_tls: false,
+            smtp_username: None,
+            smtp_password: None
+        }
+    }
+}
+
+/// How logins to accounts without a password are handled.
+///
+/// This applies to every account without a password hash,
+/// including accounts created by admin tooling.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordlessPolicy {
+    /// Logins are always denied.
+    #[default]
+    Deny,
+
+    /// Logins are allowed, but only in debug builds.
+    Dev,
+
+    /// Logins are allowed, but every login needs a grant code.
+    Code
+}
+
+/// Configuration for account logins.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AccountConfig {
+    /// How logins to accounts without a password are handled.
+    pub passwordless: PasswordlessPolicy,
+
+    /// How long, in seconds, an account can be reactivated after deletion is requested.
+    pub deletion_grace: u32
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        AccountConfig {
+            passwordless: PasswordlessPolicy::Deny,
+            // 30 days.
+            deletion_grace: 60 * 60 * 24 * 30
+        }
+    }
+}
+
+/// Configuration for brute-force protection on logins, and limits on mobile login codes.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LockoutConfig {
+    /// How long, in seconds, failed logins are counted for.
+    pub window: u32,
+
+    /// How long, in seconds, a lockout lasts.
+    pub duration: u32,
+
+    /// How many failed logins within the window lock out an account.
+    pub max_account_failures: u32,
+
+    /// How many failed logins within the window lock out an IP address.
+    pub max_address_failures: u32,
+
+    /// How many login codes can be sent to a mobile number within the window.
+    pub max_mobile_codes: u32,
+
+    /// How many login codes an IP address can request within the window.
+    pub max_address_codes: u32,
+
+    /// How many guest accounts an IP address can create within the window.
+    pub max_address_guests: u32
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        LockoutConfig {
+            window: 60 * 15,
+            duration: 60 * 15,
+            max_account_failures: 5,
+            max_address_failures: 20,
+            max_mobile_codes: 5,
+            max_address_codes: 10,
+            max_address_guests: 5
+        }
+    }
+}
+
+/// Configuration for reverse proxies in front of the server.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// The addresses of proxies which are trusted to report the client's address.
+    ///
+    /// Address headers on requests from anywhere else are ignored.
+    pub trusted: Vec<IpAddr>
+}
+
+/// Configuration for an RSA key.
+///
+/// Exactly one of `path` and `env` should be set.
+#[derive(Deserialize)]
+pub struct RsaKeyConfig {
+    /// The key ID, which the client sends to select the key.
+    pub id: String,
+
+    /// A PKCS#1 or PKCS#8 PEM file containing the key.
+    pub path: Option<String>,
+
+    /// An environment variable containing the key in PEM format.
+    pub env: Option<String>
+}
+
+/// Configuration for the private keys used to decrypt client data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RsaConfig {
+    /// Every private key which clients may encrypt data with.
+    ///
+    /// Old keys can be kept here while clients move to a new key.
+    pub keys: Vec<RsaKeyConfig>
+}
+
+impl Default for RsaConfig {
+    fn default() -> Self {
+        RsaConfig {
+            keys: vec![RsaKeyConfig {
+                id: "default".to_string(),
+                path: Some("resources/private-key.pem".to_string()),
+                env: None
+            }]
+        }
+    }
+}
+
+/// Configuration for combo logins.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ComboConfig {
+    /// The keys used to sign combo requests, keyed by the client's app ID.
+    ///
+    /// Requests from an app without a key are rejected.
+    pub app_keys: HashMap<String, String>
+}
+
+/// Configuration for the server-to-server API used by game servers.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct InternalConfig {
+    /// The secret shared with game servers, used to sign requests.
+    ///
+    /// The API rejects every request if this isn't set.
+    pub secret: Option<String>,
+
+    /// How far, in seconds, a request's timestamp may be from the server's time.
+    pub max_skew: u32
+}
+
+impl Default for InternalConfig {
+    fn default() -> Self {
+        InternalConfig {
+            secret: None,
+            max_skew: 60 * 5
+        }
+    }
+}
+
+/// Client behaviour, served from `loadConfig` and `getConfig`.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// Whether guest logins are offered.
+    pub guest: bool,
+
+    /// Whether account registration is hidden.
+    pub disable_regist: bool,
+
+    /// The page opened when the user registers an account.
+    pub register_url: String,
+
+    /// Whether an email code is required when registering.
+    pub enable_email_captcha: bool,
+
+    /// Whether the slider captcha is skipped.
+    ///
+    /// `pancake` doesn't implement the captcha, so this should usually be set.
+    pub disable_mmt: bool,
+
+    /// The third-party login methods which are offered, such as `fb` or `tw`.
+    pub thirdparty: Vec<String>,
+
+    /// Whether the user agreement is shown before logging in.
+    pub protocol: bool,
+
+    /// Whether QR code logins are offered.
+    pub qr_enabled: bool,
+
+    /// The log level used by the SDK.
+    pub log_level: String,
+
+    /// The page opened for in-game announcements.
+    pub announce_url: String,
+
+    /// Whether announcements with pictures are shown automatically.
+    pub enable_announce_pic_popup: bool
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            guest: true,
+            disable_regist: false,
+            register_url: "http://127.0.0.1:8000/account/register".to_string(),
+            enable_email_captcha: false,
+            disable_mmt: true,
+            thirdparty: Vec::new(),
+            protocol: false,
+            qr_enabled: false,
+            log_level: "INFO".to_string(),
+            announce_url: String::new(),
+            enable_announce_pic_popup: false
+        }
+    }
+}
+
+/// Configuration for canned responses to endpoints which aren't implemented.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct StubConfig {
+    /// The directory containing the stub files.
+    pub path: String
+}
+
+impl Default for StubConfig {
+    fn default() -> Self {
+        StubConfig {
+            path: "resources/stubs".to_string()
+        }
+    }
+}
+
+/// Configuration for a game server region, served by dispatch.
+#[derive(Deserialize)]
+pub struct RegionConfig {
+    /// The region's internal name, such as `os_usa`.
+    pub name: String,
+
+    /// The name shown to players.
+    pub title: String,
+
+    /// The region type, such as `DEV_PUBLIC`.
+    #[serde(default = "default_region_type")]
+    pub r#type: String,
+
+    /// The URL the client queries for the region's details.
+    pub dispatch_url: String,
+
+    /// The address of the region's game server.
+    pub gateserver_ip: String,
+
+    /// The port of the region's game server.
+    pub gateserver_port: u32,
+
+    /// The URL of the game's resources.
+    #[serde(default)]
+    pub resource_url: String,
+
+    /// The URL of the game's data.
+    #[serde(default)]
+    pub data_url: String
+}
+
+fn default_region_type() -> String {
+    "DEV_PUBLIC".to_string()
+}
+
+/// Configuration for region dispatch.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DispatchConfig {
+    /// Every region offered to clients.
+    pub regions: Vec<RegionConfig>,
+
+    /// The ID of the key in `rsa.keys` used to sign region responses.
+    pub signing_key: String,
+
+    /// The public keys used to encrypt region responses, selected by the client's `key_id`.
+    pub encryption_keys: Vec<RsaKeyConfig>,
+
+    /// A file containing the client secret key (the dispatch seed).
+    pub secret_key_path: Option<String>,
+
+    /// A file containing the key used to XOR-encrypt the custom configs.
+    pub xor_key_path: Option<String>,
+
+    /// The custom config sent in the region list, as JSON.
+    pub region_list_config: Option<String>,
+
+    /// The custom config sent with the current region, as JSON.
+    pub region_config: Option<String>
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        DispatchConfig {
+            regions: Vec::new(),
+            signing_key: "default".to_string(),
+            encryption_keys: Vec::new(),
+            secret_key_path: None,
+            xor_key_path: None,
+            region_list_config: None,
+            region_config: None
+        }
+    }
+}
+
+/// Configuration for the background jobs.
+///
+/// Each interval is in seconds, and `0` disables the job.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// How often stale grant and reactivation tickets are removed.
+    pub expire_tickets: u32,
+
+    /// How often expired tokens and codes are removed.
+    pub prune_tokens: u32,
+
+    /// How often devices which haven't been seen recently are forgotten.
+    pub forget_devices: u32,
+
+    /// How often accounts past their deletion grace period are purged.
+    pub purge_accounts: u32,
+
+    /// How long, in days, a device can go unseen before it's forgotten.
+    pub device_max_age: u32
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig {
+            expire_tickets: 60 * 10,
+            prune_tokens: 60 * 60,
+            forget_devices: 60 * 60 * 24,
+            purge_accounts: 60 * 60,
+            device_max_age: 90
+        }
+    }
+}
+
+/// A token which can use the admin API.
+#[derive(Deserialize)]
+pub struct AdminTokenConfig {
+    /// Who holds the token, recorded in logs and the account state history.
+    pub name: String,
+
+    /// The SHA-256 digest of the token, as hex.
+    ///
+    /// Only the digest is stored, so the configuration doesn't leak the token.
+    pub hash: String
+}
+
+/// Configuration for the admin API.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// The tokens which can use the API.
+    ///
+    /// The API rejects every request if this is empty.
+    pub tokens: Vec<AdminTokenConfig>
+}
+
+/// Configuration for the database migrations.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MigrationConfig {
+    /// Whether pending migrations are applied when the server launches.
+    ///
+    /// If this is disabled, the schema is still checked, but must be migrated by hand.
+    pub apply: bool
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        MigrationConfig {
+            apply: true
+        }
+    }
+}
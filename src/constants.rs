@@ -1,77 +1,145 @@
-use lazy_static::lazy_static;
-use rsa::{RsaPrivateKey, pkcs1::DecodeRsaPrivateKey};
-
 /// The MaxMind database which stores information mapping IPs to countries.
 pub const IP_DB: &[u8] = include_bytes!("../resources/GeoLite2-Country.mmdb");
-/// The RSA private key used in Grasscutter.
-const PRIVATE_KEY: &str = include_str!("../resources/private-key.pem");
-
-lazy_static! {
-    /// The private key used for decrypting client-encrypted data.
-    pub static ref RSA_PRIVATE_KEY: RsaPrivateKey = RsaPrivateKey::from_pkcs1_pem(PRIVATE_KEY).unwrap();
-}
-
 /// Used in the account registration handler.
 pub const WEBVIEW_REQUEST_TYPE_SDK: &str = "sdk";
 /// Used in the account registration handler.
 pub const WEBVIEW_URL_REGISTER: &str = "register";
 /// Used in account login responses.
 pub const REALNAME_OP_NONE: &str = "None";
+/// Used in guest login responses.
+pub const ACCOUNT_TYPE_GUEST: i32 = 0;
+/// Used in combo login responses.
+pub const ACCOUNT_TYPE_NORMAL: i32 = 1;
 
 pub const RESPONSE_SUCCESS: i16 = 0;
 pub const RESPONSE_FAILURE: i16 = -1;
 pub const RESPONSE_LOGIN_FAILED: i16 = -101;
+pub const RESPONSE_ACCOUNT_BANNED: i16 = -102;
+pub const RESPONSE_TOKEN_EXPIRED: i16 = -210;
+pub const RESPONSE_RATE_LIMITED: i16 = -3101;
+pub const RESPONSE_LOCKED_OUT: i16 = -3102;
+pub const RESPONSE_INVALID_CODE: i16 = -3205;
+pub const RESPONSE_INVALID_TICKET: i16 = -3206;
+
+/// How long, in seconds, a device grant code is valid for.
+pub const GRANT_CODE_LIFETIME: i64 = 600;
+/// How long, in seconds, before another device grant code can be sent.
+pub const GRANT_RESEND_INTERVAL: i64 = 60;
+/// How many incorrect guesses a grant ticket allows before it can't be used.
+pub const GRANT_MAX_ATTEMPTS: u32 = 5;
+/// How long, in seconds, a grant or reactivation ticket is kept before it expires.
+pub const TICKET_LIFETIME: i64 = 60 * 60;
+
+/// How long, in seconds, a mobile login code is valid for.
+pub const SMS_CODE_LIFETIME: i64 = 300;
+/// How long, in seconds, before another mobile login code can be sent.
+pub const SMS_RESEND_INTERVAL: i64 = 60;
+/// How many incorrect guesses a mobile login code allows.
+pub const SMS_MAX_ATTEMPTS: u32 = 5;
+
+/// How long, in seconds, an email verification link is valid for.
+pub const EMAIL_VERIFY_LIFETIME: i64 = 60 * 60 * 24;
+/// How long, in seconds, a password reset link is valid for.
+pub const PASSWORD_RESET_LIFETIME: i64 = 60 * 60;
+
+/// Used as the subject of device grant code notifications.
+pub const SUBJECT_GRANT_CODE: &str = "New device verification code";
+/// Used as the subject of email verification emails.
+pub const SUBJECT_VERIFY_EMAIL: &str = "Verify your email address";
+/// Used as the subject of password reset emails.
+pub const SUBJECT_RESET_PASSWORD: &str = "Reset your password";
+
+// Messages are identified by stable IDs.
+// Their text, including English, is in the catalogs in `resources/lang`.
 
 /// This is the default message used in conjunction with `RESPONSE_SUCCESS`.
-pub const MESSAGE_SUCCESS: &str = "OK";
+pub const MESSAGE_SUCCESS: &str = "success";
 /// Used when a new user account is successfully created.
-pub const MESSAGE_ACCOUNT_CREATED: &str = "Account created. Please close this page and login in the game.";
+pub const MESSAGE_ACCOUNT_CREATED: &str = "account_created";
 /// Used whenever the server encounters an error.
-pub const MESSAGE_SERVER_ERROR: &str = "System error; please try again later.";
+pub const MESSAGE_SERVER_ERROR: &str = "server_error";
 /// Used whenever the user provides invalid form data.
-pub const MESSAGE_INVALID_FORM: &str = "Invalid form data provided.";
+pub const MESSAGE_INVALID_FORM: &str = "invalid_form";
 /// Used whenever the user provides two passwords that do not match.
-pub const MESSAGE_MISMATCH_PASSWORD: &str = "The passwords do not match.";
+pub const MESSAGE_MISMATCH_PASSWORD: &str = "mismatch_password";
 /// Used whenever the user provides an invalid or bad token.
-pub const MESSAGE_BAD_TOKEN: &str = "Game account cache information error.";
+pub const MESSAGE_BAD_TOKEN: &str = "bad_token";
+/// Used whenever the user provides a token which has expired.
+pub const MESSAGE_TOKEN_EXPIRED: &str = "token_expired";
 /// Used whenever the stored token's device ID does not match the given device ID.
-pub const MESSAGE_NEW_DEVICE: &str = "For your security, please login again.";
+pub const MESSAGE_NEW_DEVICE: &str = "new_device";
+/// Used whenever the user's account state doesn't allow the request.
+pub const MESSAGE_ACCOUNT_BANNED: &str = "account_banned";
 /// Used whenever the user's password or username doesn't match.
-pub const MESSAGE_INVALID_CREDS: &str = "Incorrect username or password.";
+pub const MESSAGE_INVALID_CREDS: &str = "invalid_creds";
 /// Used whenever an account with the given username or email already exists.
-pub const MESSAGE_EXISTING_USER: &str = "An account with that username or email already exists.";
-
-/// Represents the account state.
-#[derive(Clone, Copy)]
-pub enum AccountState {
-    Deleted = 0,
-    Active = 1,
-    PendingDelete = 2,
-    LegalHold = 3
-}
-
-impl TryFrom<i32> for AccountState {
-    type Error = ();
-
-    fn try_from(value: i32) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(AccountState::Deleted),
-            1 => Ok(AccountState::Active),
-            2 => Ok(AccountState::PendingDelete),
-            3 => Ok(AccountState::LegalHold),
-            _ => Err(())
-        }
-    }
-}
-
-impl PartialEq<i32> for AccountState {
-    fn eq(&self, other: &i32) -> bool {
-        *self as i32 == *other
-    }
-}
+pub const MESSAGE_EXISTING_USER: &str = "existing_user";
+/// Used whenever the user provides a grant ticket which doesn't exist.
+pub const MESSAGE_INVALID_TICKET: &str = "invalid_ticket";
+/// Used whenever the user provides an incorrect verification code.
+pub const MESSAGE_INVALID_CODE: &str = "invalid_code";
+/// Used when an account's email address is successfully verified.
+pub const MESSAGE_EMAIL_VERIFIED: &str = "email_verified";
+/// Used whenever the user opens an invalid or expired email verification link.
+pub const MESSAGE_BAD_VERIFY_LINK: &str = "bad_verify_link";
+/// Used whenever the user provides an invalid mobile number.
+pub const MESSAGE_INVALID_MOBILE: &str = "invalid_mobile";
+/// Used whenever the user's account or address is locked out after failed logins.
+pub const MESSAGE_LOCKED_OUT: &str = "locked_out";
+/// Used whenever the user makes too many requests in a short time.
+pub const MESSAGE_RATE_LIMITED: &str = "rate_limited";
+/// Used whenever the user provides a verification code which has expired.
+pub const MESSAGE_EXPIRED_CODE: &str = "expired_code";
+/// Used whenever a signed request has an invalid signature.
+pub const MESSAGE_INVALID_SIGN: &str = "invalid_sign";
+/// Used whenever the user provides an invalid username.
+pub const MESSAGE_INVALID_USERNAME: &str = "invalid_username";
+/// Used whenever the user provides an invalid email address.
+pub const MESSAGE_INVALID_EMAIL: &str = "invalid_email";
+/// Used whenever the user provides a password which is too short or too long.
+pub const MESSAGE_INVALID_PASSWORD: &str = "invalid_password";
+/// Used whenever a form is submitted with a missing or incorrect CSRF token.
+pub const MESSAGE_INVALID_CSRF: &str = "invalid_csrf";
+/// Used whenever a password reset is requested, whether or not the account exists.
+pub const MESSAGE_RESET_SENT: &str = "reset_sent";
+/// Used whenever the user resets their password.
+pub const MESSAGE_PASSWORD_RESET: &str = "password_reset";
+/// Used whenever the user opens an invalid password reset link.
+pub const MESSAGE_BAD_RESET_LINK: &str = "bad_reset_link";
+/// Used whenever the user requests their account to be deleted.
+pub const MESSAGE_DELETION_SCHEDULED: &str = "deletion_scheduled";
+/// Used whenever a guest login is attempted while guest logins are disabled.
+pub const MESSAGE_GUEST_DISABLED: &str = "guest_disabled";
 
-impl PartialEq<AccountState> for i32 {
-    fn eq(&self, other: &AccountState) -> bool {
-        *self == *other as i32
-    }
-}
\ No newline at end of file
+/// Every message ID, which each catalog should translate.
+pub const MESSAGE_IDS: &[&str] = &[
+    MESSAGE_SUCCESS,
+    MESSAGE_ACCOUNT_CREATED,
+    MESSAGE_SERVER_ERROR,
+    MESSAGE_INVALID_FORM,
+    MESSAGE_MISMATCH_PASSWORD,
+    MESSAGE_BAD_TOKEN,
+    MESSAGE_TOKEN_EXPIRED,
+    MESSAGE_NEW_DEVICE,
+    MESSAGE_ACCOUNT_BANNED,
+    MESSAGE_INVALID_CREDS,
+    MESSAGE_EXISTING_USER,
+    MESSAGE_INVALID_TICKET,
+    MESSAGE_INVALID_CODE,
+    MESSAGE_EMAIL_VERIFIED,
+    MESSAGE_BAD_VERIFY_LINK,
+    MESSAGE_INVALID_MOBILE,
+    MESSAGE_LOCKED_OUT,
+    MESSAGE_RATE_LIMITED,
+    MESSAGE_EXPIRED_CODE,
+    MESSAGE_INVALID_SIGN,
+    MESSAGE_INVALID_USERNAME,
+    MESSAGE_INVALID_EMAIL,
+    MESSAGE_INVALID_PASSWORD,
+    MESSAGE_INVALID_CSRF,
+    MESSAGE_RESET_SENT,
+    MESSAGE_PASSWORD_RESET,
+    MESSAGE_BAD_RESET_LINK,
+    MESSAGE_DELETION_SCHEDULED,
+    MESSAGE_GUEST_DISABLED
+];
\ No newline at end of file
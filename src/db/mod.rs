@@ -1,8 +1,76 @@
 use rocket_db_pools::{sqlx, Database};
 
+#[cfg(any(
+    all(feature = "mysql", feature = "sqlite"),
+    all(feature = "mysql", feature = "postgres"),
+    all(feature = "sqlite", feature = "postgres")
+))]
+compile_error!("Only one database backend can be enabled: `mysql`, `sqlite` or `postgres`.");
+
+#[cfg(not(any(feature = "mysql", feature = "sqlite", feature = "postgres")))]
+compile_error!("A database backend must be enabled: `mysql`, `sqlite` or `postgres`.");
+
+/// The database backend, selected by the `mysql`, `sqlite` or `postgres` feature.
+#[cfg(feature = "mysql")]
+pub type Backend = sqlx::MySql;
+
+/// The database backend, selected by the `mysql`, `sqlite` or `postgres` feature.
+#[cfg(feature = "sqlite")]
+pub type Backend = sqlx::Sqlite;
+
+/// The database backend, selected by the `mysql`, `sqlite` or `postgres` feature.
+#[cfg(feature = "postgres")]
+pub type Backend = sqlx::Postgres;
+
+/// A connection to the database backend.
+pub type DbConnection = <Backend as sqlx::Database>::Connection;
+
+/// A connection pool for the database backend.
+pub type DbPool = sqlx::Pool<Backend>;
+
 /// SDK server database connection pool.
 ///
-/// This hooks to the MySQL database: `sdk`.
+/// This hooks to the database: `sdk`.
 #[derive(Database)]
 #[database("sdk")]
-pub struct SDK(sqlx::MySqlPool);
\ No newline at end of file
+pub struct SDK(DbPool);
+
+/// Runs `sqlx::query!` with the query written for the enabled backend.
+///
+/// The first query is for MySQL, which uses `?` placeholders and backtick quotes.
+/// The second is for SQLite and Postgres, which share `$1` placeholders;
+/// it can also override column types, such as SQLite's 64-bit row IDs.
+/// Both queries take the same arguments, in the same order.
+#[cfg(feature = "mysql")]
+macro_rules! query {
+    ($mysql:tt, $standard:tt $($args:tt)*) => {
+        sqlx::query!($mysql $($args)*)
+    };
+}
+
+#[cfg(not(feature = "mysql"))]
+macro_rules! query {
+    ($mysql:tt, $standard:tt $($args:tt)*) => {
+        sqlx::query!($standard $($args)*)
+    };
+}
+
+/// Runs `sqlx::query_scalar!` with the query written for the enabled backend.
+///
+/// See `query!` for how the queries are written.
+#[cfg(feature = "mysql")]
+macro_rules! query_scalar {
+    ($mysql:tt, $standard:tt $($args:tt)*) => {
+        sqlx::query_scalar!($mysql $($args)*)
+    };
+}
+
+#[cfg(not(feature = "mysql"))]
+macro_rules! query_scalar {
+    ($mysql:tt, $standard:tt $($args:tt)*) => {
+        sqlx::query_scalar!($standard $($args)*)
+    };
+}
+
+pub(crate) use query;
+pub(crate) use query_scalar;
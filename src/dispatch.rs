@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Context};
+use log::error;
+use rocket::fairing::AdHoc;
+use rsa::{pkcs1v15::SigningKey, rand_core::OsRng, signature::{SignatureEncoding, Signer}, traits::PublicKeyParts, Pkcs1v15Encrypt, RsaPublicKey};
+use sha2::Sha256;
+
+use crate::{config::{Config, DispatchConfig}, keys::{self, KeyRing}};
+
+/// The size, in bytes, of the padding added to each PKCS#1 v1.5 block.
+const PKCS1_PADDING: usize = 11;
+
+/// Protobuf messages sent by dispatch.
+///
+/// Field numbers follow the client's definitions, and may change between game versions.
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RegionSimpleInfo {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub title: String,
+        #[prost(string, tag = "3")]
+        pub r#type: String,
+        #[prost(string, tag = "4")]
+        pub dispatch_url: String
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct QueryRegionListHttpRsp {
+        #[prost(int32, tag = "1")]
+        pub retcode: i32,
+        #[prost(message, repeated, tag = "2")]
+        pub region_list: Vec<RegionSimpleInfo>,
+        #[prost(bytes = "vec", tag = "5")]
+        pub client_secret_key: Vec<u8>,
+        #[prost(bytes = "vec", tag = "6")]
+        pub client_custom_config_encrypted: Vec<u8>,
+        #[prost(bool, tag = "7")]
+        pub enable_login_pc: bool
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RegionInfo {
+        #[prost(string, tag = "1")]
+        pub gateserver_ip: String,
+        #[prost(uint32, tag = "2")]
+        pub gateserver_port: u32,
+        #[prost(string, tag = "8")]
+        pub resource_url: String,
+        #[prost(string, tag = "9")]
+        pub data_url: String,
+        #[prost(bytes = "vec", tag = "23")]
+        pub secret_key: Vec<u8>
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct QueryCurrRegionHttpRsp {
+        #[prost(int32, tag = "1")]
+        pub retcode: i32,
+        #[prost(string, tag = "2")]
+        pub msg: String,
+        #[prost(message, optional, tag = "3")]
+        pub region_info: Option<RegionInfo>,
+        #[prost(bytes = "vec", tag = "11")]
+        pub client_secret_key: Vec<u8>,
+        #[prost(bytes = "vec", tag = "12")]
+        pub region_custom_config_encrypted: Vec<u8>
+    }
+}
+
+/// The keys used to build dispatch responses.
+///
+/// These are stored in Rocket's managed state.
+pub struct DispatchKeys {
+    /// The key used to sign region responses.
+    signing_key: SigningKey<Sha256>,
+
+    /// The public keys used to encrypt region responses, keyed by the client's `key_id`.
+    encryption_keys: Vec<(String, RsaPublicKey)>,
+
+    /// The client secret key (the dispatch seed).
+    pub secret_key: Vec<u8>,
+
+    /// The key used to XOR-encrypt the custom configs.
+    xor_key: Vec<u8>
+}
+
+impl DispatchKeys {
+    /// Loads every key described by the configuration.
+    ///
+    /// The signing key is taken from the server's key ring.
+    pub fn load(config: &DispatchConfig, key_ring: &KeyRing) -> anyhow::Result<Self> {
+        let signing_key = key_ring.get(&config.signing_key)
+            .ok_or_else(|| anyhow!("the signing key '{}' is not in `rsa.keys`", config.signing_key))?;
+
+        let encryption_keys = config.encryption_keys.iter()
+            .map(|key| Ok((key.id.clone(), keys::load_public_key(key)?)))
+            .collect::<anyhow::Result<_>>()?;
+
+        let read = |path: &Option<String>| match path {
+            Some(path) => std::fs::read(path).with_context(|| format!("failed to read {}", path)),
+            None => Ok(Vec::new())
+        };
+
+        Ok(DispatchKeys {
+            signing_key: SigningKey::new(signing_key.clone()),
+            encryption_keys,
+            secret_key: read(&config.secret_key_path)?,
+            xor_key: read(&config.xor_key_path)?
+        })
+    }
+
+    /// Encrypts a custom config with the XOR key.
+    ///
+    /// The config is sent unencrypted if no XOR key is configured.
+    pub fn encrypt_config(&self, config: &Option<String>) -> Vec<u8> {
+        let mut data = config.clone().unwrap_or_default().into_bytes();
+        if !self.xor_key.is_empty() {
+            for (i, byte) in data.iter_mut().enumerate() {
+                *byte ^= self.xor_key[i % self.xor_key.len()];
+            }
+        }
+
+        data
+    }
+
+    /// Encrypts a response with the public key selected by the client.
+    ///
+    /// The data is split into blocks which fit the key, and each is encrypted separately.
+    /// Returns `None` if the key is unknown or encryption fails.
+    pub fn encrypt(&self, key_id: &str, data: &[u8]) -> Option<Vec<u8>> {
+        let (_, key) = self.encryption_keys.iter().find(|(id, _)| id == key_id)?;
+        let block_size = key.size() - PKCS1_PADDING;
+
+        let mut encrypted = Vec::with_capacity(data.len() + key.size());
+        for block in data.chunks(block_size) {
+            encrypted.extend(key.encrypt(&mut OsRng, Pkcs1v15Encrypt, block).ok()?);
+        }
+
+        Some(encrypted)
+    }
+
+    /// Signs a response with the signing key, using SHA-256.
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        self.signing_key.sign(data).to_vec()
+    }
+}
+
+/// Creates a fairing which adds the dispatch keys to Rocket's managed state.
+///
+/// This should be attached after the RSA key fairing.
+/// Ignition fails if any key can't be loaded.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Dispatch Keys", |rocket| async {
+        let keys = match (rocket.state::<Config>(), rocket.state::<KeyRing>()) {
+            (Some(config), Some(key_ring)) => DispatchKeys::load(&config.dispatch, key_ring),
+            _ => Err(anyhow!("the configuration and RSA keys must be loaded first"))
+        };
+
+        match keys {
+            Ok(keys) => Ok(rocket.manage(keys)),
+            Err(err) => {
+                error!("Failed to load the dispatch keys: {:#}", err);
+                Err(rocket)
+            }
+        }
+    })
+}
@@ -0,0 +1,212 @@
+use std::fmt::{self, Display};
+
+use log::error;
+use rocket::{http::Status, request::Request, response::{self, content::RawJson, Responder}, serde::json::Json};
+use serde_json::json;
+
+use crate::{constants, guards::language::Language, state::TransitionError, utils};
+
+/// A result type for SDK request handlers.
+pub type ShieldResult<T = RawJson<String>> = Result<T, ShieldError>;
+
+/// An error which occurs while handling an SDK request.
+///
+/// Each variant maps to a `retcode` and message which the client understands.
+/// Like every other SDK response, errors are sent with a `200` status.
+#[derive(Debug)]
+pub enum ShieldError {
+    /// An internal server error occurred.
+    System,
+
+    /// The user provided invalid data.
+    InvalidForm,
+
+    /// No account exists with the given username, email or ID.
+    ///
+    /// This uses the same message as `WrongPassword`, so accounts can't be enumerated.
+    AccountNotFound,
+
+    /// The user provided an incorrect password.
+    WrongPassword,
+
+    /// The account's state doesn't allow the request.
+    AccountBanned,
+
+    /// An account with the given username or email already exists.
+    ExistingUser,
+
+    /// The user provided a login token which doesn't exist.
+    InvalidToken,
+
+    /// The user provided a login token which has expired.
+    TokenExpired,
+
+    /// The login token was issued to a different device.
+    DeviceChanged,
+
+    /// The user provided a ticket which doesn't exist or can't be used.
+    InvalidTicket,
+
+    /// The user provided an incorrect verification code.
+    InvalidCode,
+
+    /// The user provided a verification code which has expired.
+    ExpiredCode,
+
+    /// The user provided an invalid mobile number.
+    InvalidMobile,
+
+    /// The user made too many requests in a short time.
+    RateLimited,
+
+    /// The account or address is locked out after failed logins.
+    LockedOut,
+
+    /// The request's signature doesn't match its content.
+    InvalidSign,
+
+    /// Guest logins are disabled.
+    GuestDisabled
+}
+
+impl ShieldError {
+    /// Returns the `retcode` sent to the client.
+    pub fn retcode(&self) -> i16 {
+        match self {
+            ShieldError::System
+            | ShieldError::InvalidForm
+            | ShieldError::ExistingUser
+            | ShieldError::InvalidMobile
+            | ShieldError::InvalidSign
+            | ShieldError::GuestDisabled => constants::RESPONSE_FAILURE,
+            ShieldError::AccountNotFound
+            | ShieldError::WrongPassword
+            | ShieldError::InvalidToken
+            | ShieldError::DeviceChanged => constants::RESPONSE_LOGIN_FAILED,
+            ShieldError::AccountBanned => constants::RESPONSE_ACCOUNT_BANNED,
+            ShieldError::TokenExpired => constants::RESPONSE_TOKEN_EXPIRED,
+            ShieldError::InvalidTicket => constants::RESPONSE_INVALID_TICKET,
+            ShieldError::InvalidCode | ShieldError::ExpiredCode => constants::RESPONSE_INVALID_CODE,
+            ShieldError::RateLimited => constants::RESPONSE_RATE_LIMITED,
+            ShieldError::LockedOut => constants::RESPONSE_LOCKED_OUT
+        }
+    }
+
+    /// Returns the message sent to the client.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ShieldError::System => constants::MESSAGE_SERVER_ERROR,
+            ShieldError::InvalidForm => constants::MESSAGE_INVALID_FORM,
+            ShieldError::AccountNotFound | ShieldError::WrongPassword => constants::MESSAGE_INVALID_CREDS,
+            ShieldError::AccountBanned => constants::MESSAGE_ACCOUNT_BANNED,
+            ShieldError::ExistingUser => constants::MESSAGE_EXISTING_USER,
+            ShieldError::InvalidToken => constants::MESSAGE_BAD_TOKEN,
+            ShieldError::TokenExpired => constants::MESSAGE_TOKEN_EXPIRED,
+            ShieldError::DeviceChanged => constants::MESSAGE_NEW_DEVICE,
+            ShieldError::InvalidTicket => constants::MESSAGE_INVALID_TICKET,
+            ShieldError::InvalidCode => constants::MESSAGE_INVALID_CODE,
+            ShieldError::ExpiredCode => constants::MESSAGE_EXPIRED_CODE,
+            ShieldError::InvalidMobile => constants::MESSAGE_INVALID_MOBILE,
+            ShieldError::RateLimited => constants::MESSAGE_RATE_LIMITED,
+            ShieldError::LockedOut => constants::MESSAGE_LOCKED_OUT,
+            ShieldError::InvalidSign => constants::MESSAGE_INVALID_SIGN,
+            ShieldError::GuestDisabled => constants::MESSAGE_GUEST_DISABLED
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ShieldError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let language = Language::from_headers(request.headers());
+        utils::message_response(language, self.retcode(), self.message(), ()).respond_to(request)
+    }
+}
+
+impl From<sqlx::Error> for ShieldError {
+    fn from(err: sqlx::Error) -> Self {
+        error!("Database query failed: {}", err);
+        ShieldError::System
+    }
+}
+
+impl From<TransitionError> for ShieldError {
+    fn from(err: TransitionError) -> Self {
+        match err {
+            TransitionError::NotFound => ShieldError::AccountNotFound,
+            TransitionError::NotAllowed(..) => ShieldError::AccountBanned,
+            TransitionError::Database(err) => err.into()
+        }
+    }
+}
+
+/// A result type for admin API handlers.
+pub type AdminResult<T> = Result<Json<T>, AdminError>;
+
+/// An error which occurs while handling an admin API request.
+///
+/// Unlike SDK errors, these are sent with a matching HTTP status,
+/// and a JSON body containing an `error` message.
+#[derive(Debug)]
+pub enum AdminError {
+    /// An internal server error occurred.
+    System,
+
+    /// The request is missing a parameter, or has an invalid one.
+    BadRequest(&'static str),
+
+    /// The account, device or token doesn't exist.
+    NotFound,
+
+    /// The account's current state doesn't allow the change.
+    Conflict(String)
+}
+
+impl AdminError {
+    /// Returns the HTTP status sent to the client.
+    pub fn status(&self) -> Status {
+        match self {
+            AdminError::System => Status::InternalServerError,
+            AdminError::BadRequest(_) => Status::BadRequest,
+            AdminError::NotFound => Status::NotFound,
+            AdminError::Conflict(_) => Status::Conflict
+        }
+    }
+}
+
+impl Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminError::System => write!(f, "Internal server error."),
+            AdminError::BadRequest(message) => write!(f, "{}", message),
+            AdminError::NotFound => write!(f, "Not found."),
+            AdminError::Conflict(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl std::error::Error for AdminError {}
+
+impl<'r> Responder<'r, 'static> for AdminError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        (self.status(), Json(json!({ "error": self.to_string() }))).respond_to(request)
+    }
+}
+
+impl From<sqlx::Error> for AdminError {
+    fn from(err: sqlx::Error) -> Self {
+        error!("Database query failed: {}", err);
+        AdminError::System
+    }
+}
+
+impl From<TransitionError> for AdminError {
+    fn from(err: TransitionError) -> Self {
+        match err {
+            TransitionError::NotFound => AdminError::NotFound,
+            TransitionError::NotAllowed(from, to) => AdminError::Conflict(
+                format!("The account can't be moved from {:?} to {:?}.", from, to)
+            ),
+            TransitionError::Database(err) => err.into()
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use rocket::{http::Status, request::{FromRequest, Outcome}, Request};
+
+use crate::{config::Config, utils};
+
+const HEADER: &str = "Authorization";
+const MISSING_ERROR: &str = "Invalid request, missing bearer token in 'Authorization' header.";
+const INVALID_ERROR: &str = "Invalid admin token.";
+
+/// Rocket guard which enforces a valid admin API token.
+///
+/// The token is sent as `Authorization: Bearer <token>`, and checked against `admin.tokens`.
+/// The name of the token's holder is placed in the `name` field.
+pub struct AdminToken {
+    pub name: String
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminToken {
+    type Error = &'r str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = request.headers().get_one(HEADER)
+            .and_then(|header| header.strip_prefix("Bearer "))
+        else {
+            return Outcome::Error((Status::Unauthorized, MISSING_ERROR));
+        };
+
+        let Some(config) = request.rocket().state::<Config>() else {
+            return Outcome::Error((Status::InternalServerError, INVALID_ERROR));
+        };
+
+        let hash = utils::hash_token(token.trim());
+        match config.admin.tokens.iter().find(|admin| admin.hash.eq_ignore_ascii_case(&hash)) {
+            Some(admin) => Outcome::Success(AdminToken { name: admin.name.clone() }),
+            None => Outcome::Error((Status::Unauthorized, INVALID_ERROR))
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use rocket::{http::Status, request::{FromRequest, Outcome}, Request};
+
+use crate::keys::KeyRing;
+
+const HEADER: &str = "x-rpc-key_id";
+
+/// Rocket guard which selects the key used to decrypt client data.
+///
+/// The key ID is read from the `x-rpc-key_id` header.
+/// If the header is missing, every key is tried when decrypting.
+pub struct ClientKey<'r> {
+    keys: &'r KeyRing,
+    key_id: Option<&'r str>
+}
+
+impl ClientKey<'_> {
+    /// Decrypts data sent by the client.
+    pub fn decrypt(&self, data: &[u8]) -> Option<Vec<u8>> {
+        self.keys.decrypt(self.key_id, data)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientKey<'r> {
+    type Error = &'r str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(keys) = request.rocket().state::<KeyRing>() else {
+            return Outcome::Error((Status::InternalServerError, "no RSA keys are loaded"));
+        };
+
+        Outcome::Success(ClientKey {
+            keys,
+            key_id: request.headers().get_one(HEADER)
+        })
+    }
+}
@@ -0,0 +1,25 @@
+use rocket::{request::{FromRequest, Outcome}, Request};
+
+const HEADER: &str = "x-rpc-client_type";
+
+/// Rocket guard which reads the `x-rpc-client_type` header.
+///
+/// The client type is placed in the `0` part of the struct.
+/// This is `0` if the header is missing or invalid.
+///
+/// Known values are `1` (iOS), `2` (Android), `3` (PC) and `4` (web).
+pub struct ClientType(pub u8);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientType {
+    type Error = &'r str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let client_type = request.headers()
+            .get_one(HEADER)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+
+        Outcome::Success(ClientType(client_type))
+    }
+}
@@ -0,0 +1,41 @@
+use rocket::{http::{Cookie, SameSite}, request::{FromRequest, Outcome}, Request};
+
+use crate::utils;
+
+const COOKIE: &str = "csrf_token";
+
+/// Rocket guard which provides a CSRF token for HTML forms.
+///
+/// The token is stored in a cookie, and must be sent back in the form.
+/// A new token is created if the cookie is missing.
+pub struct CsrfToken(pub String);
+
+impl CsrfToken {
+    /// Checks the token submitted with a form.
+    pub fn verify(&self, submitted: &str) -> bool {
+        // Compare every byte, so the comparison takes the same time for any mismatch.
+        self.0.len() == submitted.len() && self.0.bytes()
+            .zip(submitted.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfToken {
+    type Error = &'r str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cookies = request.cookies();
+        if let Some(cookie) = cookies.get(COOKIE) {
+            return Outcome::Success(CsrfToken(cookie.value().to_string()));
+        }
+
+        let token = utils::random_token();
+        cookies.add(Cookie::build((COOKIE, token.clone()))
+            .path("/account")
+            .http_only(true)
+            .same_site(SameSite::Strict));
+
+        Outcome::Success(CsrfToken(token))
+    }
+}
@@ -1,11 +1,18 @@
+use std::net::IpAddr;
+
 use rocket::{request::{FromRequest, Outcome}, Request};
 
+use crate::config::Config;
+
 const PROXY_HEADER: &str = "X-Real-IP";
 const CF_HEADER: &str = "CF-Connecting-IP";
 const NO_IP_ADDRESS: &str = "Invalid request, missing client IP address.";
 
 /// Rocket guard which fetches the client's IP address.
 /// 
+/// The address headers are only used on requests from a trusted proxy.
+/// Otherwise, the address of the connecting peer is used.
+/// 
 /// The IP address is placed in the `0` part of the struct.
 pub struct IpAddress(pub String);
 
@@ -14,20 +21,26 @@ impl<'r> FromRequest<'r> for IpAddress {
     type Error = &'r str;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        // Check for the CloudFlare header.
-        if let Some(ip) = request.headers().get_one(CF_HEADER) {
-            return Outcome::Success(IpAddress(ip.to_string()));
-        }
+        // Get the address of the connecting peer.
+        let Some(peer) = request.remote().map(|remote| remote.ip()) else {
+            return Outcome::Error((rocket::http::Status::BadRequest, NO_IP_ADDRESS));
+        };
 
-        // Check for the proxy header.
-        if let Some(ip) = request.headers().get_one(PROXY_HEADER) {
-            return Outcome::Success(IpAddress(ip.to_string()));
-        }
+        // Check if the peer is a trusted proxy.
+        let trusted = request.rocket().state::<Config>()
+            .is_some_and(|config| config.proxy.trusted.contains(&peer));
+
+        if trusted {
+            // Check for the CloudFlare header, then the proxy header.
+            let forwarded = [CF_HEADER, PROXY_HEADER].into_iter()
+                .filter_map(|header| request.headers().get_one(header))
+                .find_map(|ip| ip.trim().parse::<IpAddr>().ok());
 
-        // Get the client's IP address.
-        match request.client_ip() {
-            Some(ip) => Outcome::Success(IpAddress(ip.to_string())),
-            None => Outcome::Error((rocket::http::Status::BadRequest, NO_IP_ADDRESS))
+            if let Some(ip) = forwarded {
+                return Outcome::Success(IpAddress(ip.to_string()));
+            }
         }
+
+        Outcome::Success(IpAddress(peer.to_string()))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,45 @@
+use rocket::{http::HeaderMap, request::{FromRequest, Outcome}, Request};
+
+use crate::i18n;
+
+const HEADER: &str = "x-rpc-language";
+const ACCEPT_HEADER: &str = "Accept-Language";
+
+/// Rocket guard which determines the client's language.
+///
+/// The `x-rpc-language` header is preferred, followed by `Accept-Language`.
+/// The language code is placed in the `0` part of the struct.
+/// This is the default language if neither header has a supported language.
+#[derive(Clone, Copy)]
+pub struct Language(pub &'static str);
+
+impl Language {
+    /// Determines the language from the request headers.
+    pub fn from_headers(headers: &HeaderMap<'_>) -> Self {
+        if let Some(language) = headers.get_one(HEADER).and_then(i18n::match_language) {
+            return Language(language);
+        }
+
+        headers.get_one(ACCEPT_HEADER)
+            .and_then(|value| value
+                .split(',')
+                .filter_map(|tag| tag.split(';').next())
+                .find_map(i18n::match_language))
+            .map(Language)
+            .unwrap_or(Language(i18n::DEFAULT_LANGUAGE))
+    }
+
+    /// Translates a message, by its ID, into this language.
+    pub fn translate(self, id: &'static str) -> &'static str {
+        i18n::translate(self.0, id)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Language {
+    type Error = &'r str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Language::from_headers(request.headers()))
+    }
+}
@@ -1,2 +1,7 @@
 pub mod device_id;
-pub mod ip_address;
\ No newline at end of file
+pub mod ip_address;
+pub mod client_type;
+pub mod language;
+pub mod client_key;
+pub mod csrf;
+pub mod admin_token;
\ No newline at end of file
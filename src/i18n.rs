@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use log::{error, warn};
+use rocket::fairing::AdHoc;
+
+use crate::constants;
+
+/// The language used when the client's language isn't supported.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The message catalogs, keyed by language code.
+///
+/// Each catalog maps a message ID (see `constants`) to its translation.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../resources/lang/en.json")),
+    ("zh-cn", include_str!("../resources/lang/zh-cn.json")),
+    ("zh-tw", include_str!("../resources/lang/zh-tw.json")),
+    ("ja", include_str!("../resources/lang/ja.json")),
+    ("ko", include_str!("../resources/lang/ko.json")),
+    ("fr", include_str!("../resources/lang/fr.json")),
+    ("de", include_str!("../resources/lang/de.json")),
+    ("es", include_str!("../resources/lang/es.json")),
+    ("ru", include_str!("../resources/lang/ru.json"))
+];
+
+lazy_static! {
+    /// The parsed message catalogs.
+    ///
+    /// Catalogs which can't be parsed are left out, but `fairing` stops the server launching with one.
+    static ref MESSAGES: HashMap<&'static str, HashMap<String, String>> = CATALOGS
+        .iter()
+        .filter_map(|(language, catalog)| Some((*language, serde_json::from_str(catalog).ok()?)))
+        .collect();
+}
+
+/// Finds the supported language which best matches a language tag.
+///
+/// Tags are matched case-insensitively, first exactly and then by their primary language.
+/// For example, `zh-HK` matches `zh-tw`, and `en-US` matches `en`.
+pub fn match_language(tag: &str) -> Option<&'static str> {
+    let tag = tag.trim().to_lowercase().replace('_', "-");
+    let find = |code: &str| CATALOGS.iter()
+        .map(|(language, _)| *language)
+        .find(|language| *language == code);
+
+    if let Some(language) = find(&tag) {
+        return Some(language);
+    }
+
+    // Chinese is split by script, not by region.
+    match tag.as_str() {
+        "zh-hk" | "zh-mo" | "zh-hant" => return find("zh-tw"),
+        "zh" | "zh-sg" | "zh-hans" => return find("zh-cn"),
+        _ => ()
+    }
+
+    find(tag.split('-').next()?)
+}
+
+/// Translates a message into the given language.
+///
+/// English is used if the message has no translation, and the ID if it has no English text either.
+pub fn translate(language: &str, id: &'static str) -> &'static str {
+    let messages: &'static HashMap<_, HashMap<String, String>> = &MESSAGES;
+    [language, DEFAULT_LANGUAGE].into_iter()
+        .find_map(|language| messages.get(language)?.get(id))
+        .map(String::as_str)
+        .unwrap_or(id)
+}
+
+/// Creates a fairing which checks the message catalogs when the server starts.
+///
+/// The server won't launch if a catalog can't be parsed, or if English is missing a message.
+/// Other missing or unknown messages are logged.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Message Catalogs", |rocket| async {
+        for (language, catalog) in CATALOGS {
+            let messages: HashMap<String, String> = match serde_json::from_str(catalog) {
+                Ok(messages) => messages,
+                Err(err) => {
+                    error!("The '{}' message catalog is invalid: {}", language, err);
+                    return Err(rocket);
+                }
+            };
+
+            let missing: Vec<_> = constants::MESSAGE_IDS.iter()
+                .filter(|id| !messages.contains_key(**id))
+                .collect();
+            if !missing.is_empty() {
+                // English is the fallback for every other language, so it must be complete.
+                if *language == DEFAULT_LANGUAGE {
+                    error!("The '{}' message catalog is missing {:?}", language, missing);
+                    return Err(rocket);
+                }
+
+                warn!("The '{}' message catalog is missing {:?}, so English is used for them", language, missing);
+            }
+
+            for id in messages.keys().filter(|id| !constants::MESSAGE_IDS.contains(&id.as_str())) {
+                warn!("The '{}' message catalog has an unknown message '{}'", language, id);
+            }
+        }
+
+        Ok(rocket)
+    })
+}
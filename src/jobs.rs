@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use log::{error, info};
+use rocket::{fairing::AdHoc, tokio, Shutdown};
+use rocket_db_pools::Database;
+use crate::{config::Config, constants, purge, utils};
+use crate::db::{query, DbConnection, DbPool, SDK};
+
+/// A task which runs periodically in the background.
+#[rocket::async_trait]
+pub trait Job: Send + Sync {
+    /// The name of the job, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Runs the job once.
+    ///
+    /// Returns a summary of what was done, which is logged.
+    async fn run(&self, db: &mut DbConnection) -> sqlx::Result<String>;
+}
+
+/// Removes grant and reactivation tickets which were never used.
+pub struct ExpireTickets;
+
+#[rocket::async_trait]
+impl Job for ExpireTickets {
+    fn name(&self) -> &'static str {
+        "expire tickets"
+    }
+
+    async fn run(&self, db: &mut DbConnection) -> sqlx::Result<String> {
+        let cutoff = utils::current_time() - constants::TICKET_LIFETIME;
+
+        let grant = query!(
+            "DELETE FROM `grant_tickets` WHERE `epoch_created` < ?",
+            "DELETE FROM grant_tickets WHERE epoch_created < $1",
+            cutoff
+        ).execute(&mut *db).await?.rows_affected();
+        let reactivate = query!(
+            "DELETE FROM `reactivate_tickets` WHERE `epoch_created` < ?",
+            "DELETE FROM reactivate_tickets WHERE epoch_created < $1",
+            cutoff
+        ).execute(&mut *db).await?.rows_affected();
+
+        Ok(format!("removed {} grant and {} reactivation ticket(s)", grant, reactivate))
+    }
+}
+
+/// Removes expired tokens, links and codes, along with old lockout records.
+pub struct PruneTokens {
+    /// How long, in seconds, failed logins are counted for.
+    pub lockout_window: u32
+}
+
+#[rocket::async_trait]
+impl Job for PruneTokens {
+    fn name(&self) -> &'static str {
+        "prune tokens"
+    }
+
+    async fn run(&self, db: &mut DbConnection) -> sqlx::Result<String> {
+        let current_time = utils::current_time();
+
+        let login = query!(
+            "DELETE FROM `login_tokens` WHERE `epoch_expires` <= ?",
+            "DELETE FROM login_tokens WHERE epoch_expires <= $1",
+            current_time
+        ).execute(&mut *db).await?.rows_affected();
+        let combo = query!(
+            "DELETE FROM `combo_tokens` WHERE `epoch_expires` <= ?",
+            "DELETE FROM combo_tokens WHERE epoch_expires <= $1",
+            current_time
+        ).execute(&mut *db).await?.rows_affected();
+        let verifications = query!(
+            "DELETE FROM `email_verifications` WHERE `epoch_created` < ?",
+            "DELETE FROM email_verifications WHERE epoch_created < $1",
+            current_time - constants::EMAIL_VERIFY_LIFETIME
+        ).execute(&mut *db).await?.rows_affected();
+        let resets = query!(
+            "DELETE FROM `password_resets` WHERE `epoch_created` < ?",
+            "DELETE FROM password_resets WHERE epoch_created < $1",
+            current_time - constants::PASSWORD_RESET_LIFETIME
+        ).execute(&mut *db).await?.rows_affected();
+        let captchas = query!(
+            "DELETE FROM `mobile_captchas` WHERE `epoch_sent` < ?",
+            "DELETE FROM mobile_captchas WHERE epoch_sent < $1",
+            current_time - constants::SMS_CODE_LIFETIME
+        ).execute(&mut *db).await?.rows_affected();
+        let failures = query!(
+            "DELETE FROM `login_failures` WHERE `epoch_failed` <= ?",
+            "DELETE FROM login_failures WHERE epoch_failed <= $1",
+            current_time - i64::from(self.lockout_window)
+        ).execute(&mut *db).await?.rows_affected();
+        let lockouts = query!(
+            "DELETE FROM `login_lockouts` WHERE `epoch_until` <= ?",
+            "DELETE FROM login_lockouts WHERE epoch_until <= $1",
+            current_time
+        ).execute(&mut *db).await?.rows_affected();
+
+        Ok(format!(
+            "removed {} login token(s), {} combo token(s), {} email verification(s), {} password reset(s), {} mobile code(s), {} login failure(s) and {} lockout(s)",
+            login, combo, verifications, resets, captchas, failures, lockouts
+        ))
+    }
+}
+
+/// Forgets devices which haven't been seen for a while.
+///
+/// A forgotten device needs a new grant the next time it logs in.
+pub struct ForgetDevices {
+    /// How long, in seconds, a device can go unseen.
+    pub max_age: u32
+}
+
+#[rocket::async_trait]
+impl Job for ForgetDevices {
+    fn name(&self) -> &'static str {
+        "forget devices"
+    }
+
+    async fn run(&self, db: &mut DbConnection) -> sqlx::Result<String> {
+        let forgotten = query!(
+            "DELETE FROM `devices` WHERE `epoch_lastseen` < ?",
+            "DELETE FROM devices WHERE epoch_lastseen < $1",
+            utils::current_time() - i64::from(self.max_age)
+        ).execute(&mut *db).await?.rows_affected();
+
+        Ok(format!("forgot {} device(s)", forgotten))
+    }
+}
+
+/// Purges accounts whose deletion grace period has expired.
+///
+/// See `purge::purge_expired`.
+pub struct PurgeAccounts;
+
+#[rocket::async_trait]
+impl Job for PurgeAccounts {
+    fn name(&self) -> &'static str {
+        "purge accounts"
+    }
+
+    async fn run(&self, db: &mut DbConnection) -> sqlx::Result<String> {
+        let purged = purge::purge_expired(db).await?;
+        Ok(format!("purged {} account(s)", purged))
+    }
+}
+
+/// Returns every job, along with its interval in seconds.
+fn jobs(config: &Config) -> Vec<(Box<dyn Job>, u32)> {
+    let jobs = &config.jobs;
+    vec![
+        (Box::new(ExpireTickets), jobs.expire_tickets),
+        (Box::new(PruneTokens { lockout_window: config.lockout.window }), jobs.prune_tokens),
+        (Box::new(ForgetDevices { max_age: jobs.device_max_age * 60 * 60 * 24 }), jobs.forget_devices),
+        (Box::new(PurgeAccounts), jobs.purge_accounts)
+    ]
+}
+
+/// Runs a job every `interval` seconds, until the server shuts down.
+async fn schedule(job: Box<dyn Job>, pool: DbPool, interval: u32, mut shutdown: Shutdown) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval.into()));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            _ = &mut shutdown => break
+        }
+
+        let result = match pool.acquire().await {
+            Ok(mut db) => job.run(&mut db).await,
+            Err(err) => Err(err)
+        };
+        match result {
+            Ok(summary) => info!("Job '{}' finished: {}", job.name(), summary),
+            Err(err) => error!("Job '{}' failed: {}", job.name(), err)
+        }
+    }
+
+    info!("Job '{}' stopped", job.name());
+}
+
+/// Creates a fairing which runs the background jobs once the server has started.
+///
+/// This should be attached after the configuration and database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Job Scheduler", |rocket| Box::pin(async move {
+        let (Some(config), Some(db)) = (rocket.state::<Config>(), SDK::fetch(rocket)) else {
+            error!("Background jobs are disabled, as the database isn't available");
+            return;
+        };
+
+        for (job, interval) in jobs(config) {
+            if interval == 0 {
+                info!("Job '{}' is disabled", job.name());
+                continue;
+            }
+
+            tokio::spawn(schedule(job, DbPool::clone(db), interval, rocket.shutdown()));
+        }
+    }))
+}
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, bail, Context};
+use log::{error, info};
+use rocket::fairing::AdHoc;
+use rsa::{pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey}, pkcs8::{DecodePrivateKey, DecodePublicKey}, Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+
+use crate::config::{Config, RsaConfig, RsaKeyConfig};
+
+/// The private keys used for decrypting client-encrypted data.
+///
+/// The key ring used by the server is stored in Rocket's managed state.
+pub struct KeyRing {
+    keys: Vec<(String, RsaPrivateKey)>
+}
+
+impl KeyRing {
+    /// Loads every key described by the configuration.
+    ///
+    /// This fails if any key can't be read or parsed, or if no keys are configured.
+    pub fn load(config: &RsaConfig) -> anyhow::Result<Self> {
+        if config.keys.is_empty() {
+            bail!("no RSA keys are configured");
+        }
+
+        let mut keys = Vec::with_capacity(config.keys.len());
+        for key in &config.keys {
+            if keys.iter().any(|(id, _)| id == &key.id) {
+                bail!("the RSA key ID '{}' is used more than once", key.id);
+            }
+
+            let private_key = load_key(key)
+                .with_context(|| format!("failed to load the RSA key '{}'", key.id))?;
+            keys.push((key.id.clone(), private_key));
+        }
+
+        Ok(KeyRing { keys })
+    }
+
+    /// Returns the key with the given ID.
+    pub fn get(&self, key_id: &str) -> Option<&RsaPrivateKey> {
+        self.keys.iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, key)| key)
+    }
+
+    /// Decrypts data sent by the client.
+    ///
+    /// If `key_id` names a known key, only that key is used.
+    /// Otherwise, each key is tried in order.
+    pub fn decrypt(&self, key_id: Option<&str>, data: &[u8]) -> Option<Vec<u8>> {
+        if let Some(key) = key_id.and_then(|key_id| self.get(key_id)) {
+            return key.decrypt(Pkcs1v15Encrypt, data).ok();
+        }
+
+        self.keys.iter().find_map(|(_, key)| key.decrypt(Pkcs1v15Encrypt, data).ok())
+    }
+}
+
+/// Reads the PEM text of a key from its file or environment variable.
+fn read_pem(config: &RsaKeyConfig) -> anyhow::Result<String> {
+    Ok(match (&config.path, &config.env) {
+        (Some(path), None) => std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path))?,
+        (None, Some(env)) => std::env::var(env)
+            .with_context(|| format!("failed to read the environment variable {}", env))?,
+        _ => bail!("exactly one of `path` and `env` must be set")
+    })
+}
+
+/// Reads and parses a single private key.
+fn load_key(config: &RsaKeyConfig) -> anyhow::Result<RsaPrivateKey> {
+    let pem = read_pem(config)?;
+
+    RsaPrivateKey::from_pkcs1_pem(&pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs8_pem(&pem))
+        .map_err(|_| anyhow!("the key is not a valid PKCS#1 or PKCS#8 PEM"))
+}
+
+/// Reads and parses a single public key.
+pub fn load_public_key(config: &RsaKeyConfig) -> anyhow::Result<RsaPublicKey> {
+    let pem = read_pem(config)
+        .with_context(|| format!("failed to load the RSA key '{}'", config.id))?;
+
+    RsaPublicKey::from_pkcs1_pem(&pem)
+        .or_else(|_| RsaPublicKey::from_public_key_pem(&pem))
+        .map_err(|_| anyhow!("the RSA key '{}' is not a valid PKCS#1 or PKCS#8 public key", config.id))
+}
+
+/// Creates a fairing which adds the configured key ring to Rocket's managed state.
+///
+/// This should be attached after the configuration fairing.
+/// Ignition fails if any key can't be loaded.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("RSA Keys", |rocket| async {
+        let keys = match rocket.state::<Config>() {
+            Some(config) => KeyRing::load(&config.rsa),
+            None => KeyRing::load(&RsaConfig::default())
+        };
+
+        match keys {
+            Ok(keys) => {
+                info!("Loaded {} RSA key(s)", keys.keys.len());
+                Ok(rocket.manage(keys))
+            },
+            Err(err) => {
+                error!("Failed to load the RSA keys: {:#}", err);
+                Err(rocket)
+            }
+        }
+    })
+}
@@ -1,12 +1,30 @@
 #[macro_use] extern crate rocket;
 
 mod db;
+mod error;
+mod config;
 mod utils;
+mod i18n;
+mod state;
 mod routes;
 mod guards;
+mod sms;
+mod mail;
+mod lockout;
+mod keys;
+mod stubs;
+mod dispatch;
+mod purge;
+mod jobs;
+mod migrate;
 mod constants;
 
+pub mod admin;
+
+use rocket::fairing::AdHoc;
 use rocket_db_pools::Database;
+use rocket_dyn_templates::Template;
+use crate::config::Config;
 use crate::db::SDK;
 
 /// A result type for request handlers that returns a message for an error.
@@ -31,10 +49,30 @@ pub async fn launch() -> Result<(), rocket::Error> {
     // Create the web app.
     let _rocket = rocket::build()
         .attach(SDK::init())
+        .attach(AdHoc::config::<Config>())
+        .attach(migrate::fairing())
+        .attach(i18n::fairing())
+        .attach(sms::fairing())
+        .attach(mail::fairing())
+        .attach(keys::fairing())
+        .attach(dispatch::fairing())
+        .attach(Template::fairing())
+        .attach(jobs::fairing())
+        .attach(routes::hk4e::combo::fairing())
+        .attach(stubs::fairing())
         .mount("/", routes![health, favicon])
         .mount("/hk4e_global", routes::hk4e::shield::mount())
         .mount("/hk4e_cn", routes::hk4e::shield::mount())
+        .mount("/hk4e_global", routes::hk4e::guest::mount())
+        .mount("/hk4e_cn", routes::hk4e::guest::mount())
+        .mount("/hk4e_global", routes::hk4e::combo::mount())
+        .mount("/hk4e_cn", routes::hk4e::combo::mount())
         .mount("/account", routes::account::mount())
+        .mount("/account/device", routes::device::mount())
+        .mount("/internal", routes::internal::mount())
+        .mount("/admin", routes::admin::mount())
+        .register("/admin", routes::admin::catchers())
+        .mount("/", routes::dispatch::mount())
         .launch()
         .await?;
 
@@ -0,0 +1,130 @@
+use std::fmt::{self, Display};
+
+use crate::{config::LockoutConfig, db::{query, query_scalar, DbConnection}, utils};
+
+/// Something which can be locked out.
+#[derive(Clone, Copy)]
+pub enum Subject<'a> {
+    /// An account, by its unique ID.
+    Account(i32),
+
+    /// A client IP address.
+    Address(&'a str),
+
+    /// A mobile number which login codes are sent to.
+    Mobile(&'a str),
+
+    /// A client IP address which requests login codes.
+    SmsAddress(&'a str),
+
+    /// A client IP address which creates guest accounts.
+    GuestAddress(&'a str)
+}
+
+impl Subject<'_> {
+    /// Returns how many failures within the window lock the subject out.
+    fn max_failures(&self, config: &LockoutConfig) -> u32 {
+        match self {
+            Subject::Account(_) => config.max_account_failures,
+            Subject::Address(_) => config.max_address_failures,
+            Subject::Mobile(_) => config.max_mobile_codes,
+            Subject::SmsAddress(_) => config.max_address_codes,
+            Subject::GuestAddress(_) => config.max_address_guests
+        }
+    }
+}
+
+impl Display for Subject<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Account(uid) => write!(f, "account:{}", uid),
+            Subject::Address(address) => write!(f, "ip:{}", address),
+            Subject::Mobile(mobile) => write!(f, "mobile:{}", mobile),
+            Subject::SmsAddress(address) => write!(f, "sms-ip:{}", address),
+            Subject::GuestAddress(address) => write!(f, "guest-ip:{}", address)
+        }
+    }
+}
+
+/// Checks if any of the subjects are locked out.
+///
+/// Returns `true` if at least one of them is.
+pub async fn is_locked(db: &mut DbConnection, subjects: &[Subject<'_>]) -> sqlx::Result<bool> {
+    let current_time = utils::current_time();
+    for subject in subjects {
+        let result = query!(
+            "SELECT `epoch_until` FROM `login_lockouts` WHERE `subject` = ? AND `epoch_until` > ?",
+            "SELECT epoch_until FROM login_lockouts WHERE subject = $1 AND epoch_until > $2",
+            subject.to_string(), current_time
+        ).fetch_optional(&mut *db).await?;
+
+        if result.is_some() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Records a failed login for each of the subjects.
+///
+/// For the mobile subjects, this records a login code being sent instead,
+/// and for `GuestAddress`, a guest account being created.
+/// Subjects which reach their limit within the window are locked out.
+pub async fn record_failure(
+    db: &mut DbConnection,
+    config: &LockoutConfig,
+    subjects: &[Subject<'_>]
+) -> sqlx::Result<()> {
+    let current_time = utils::current_time();
+    let window_start = current_time - i64::from(config.window);
+
+    for subject in subjects {
+        let key = subject.to_string();
+
+        // Record the failure.
+        query!(
+            "INSERT INTO `login_failures` (`subject`, `epoch_failed`) VALUES (?, ?)",
+            "INSERT INTO login_failures (subject, epoch_failed) VALUES ($1, $2)",
+            key, current_time
+        ).execute(&mut *db).await?;
+
+        // Count the failures within the window.
+        let failures = query_scalar!(
+            "SELECT COUNT(*) FROM `login_failures` WHERE `subject` = ? AND `epoch_failed` > ?",
+            r#"SELECT COUNT(*) AS "count!: i64" FROM login_failures WHERE subject = $1 AND epoch_failed > $2"#,
+            key, window_start
+        ).fetch_one(&mut *db).await?;
+
+        if failures as u32 >= subject.max_failures(config) {
+            let until = current_time + i64::from(config.duration);
+            query!(
+                "INSERT INTO `login_lockouts` (`subject`, `epoch_until`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `epoch_until` = ?",
+                "INSERT INTO login_lockouts (subject, epoch_until) VALUES ($1, $2) ON CONFLICT (subject) DO UPDATE SET epoch_until = $3",
+                key, until, until
+            ).execute(&mut *db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Clears every failure and lockout for the subject.
+///
+/// This is used after a successful login, and to unlock a subject early.
+pub async fn clear(db: &mut DbConnection, subject: Subject<'_>) -> sqlx::Result<()> {
+    let key = subject.to_string();
+
+    query!(
+        "DELETE FROM `login_failures` WHERE `subject` = ?",
+        "DELETE FROM login_failures WHERE subject = $1",
+        key
+    ).execute(&mut *db).await?;
+    query!(
+        "DELETE FROM `login_lockouts` WHERE `subject` = ?",
+        "DELETE FROM login_lockouts WHERE subject = $1",
+        key
+    ).execute(&mut *db).await?;
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use lettre::{message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{error, info};
+use rocket::fairing::AdHoc;
+use rocket::tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::config::{Config, MailConfig, MailTransportKind};
+
+/// An email which should be delivered to an account holder.
+pub struct Mail {
+    /// The email address of the recipient.
+    pub to: String,
+
+    /// The subject line of the email.
+    pub subject: &'static str,
+
+    /// The plain-text content of the email.
+    pub body: String
+}
+
+/// Delivers emails to account holders.
+///
+/// The transport used by the server is stored in Rocket's managed state.
+#[rocket::async_trait]
+pub trait MailTransport: Send + Sync {
+    /// Sends the email to its recipient.
+    async fn send(&self, mail: Mail) -> anyhow::Result<()>;
+}
+
+/// A transport which writes every email to the log.
+///
+/// This is the default transport, and allows testing without a mail server.
+pub struct LogTransport;
+
+#[rocket::async_trait]
+impl MailTransport for LogTransport {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        info!("Mail for {}: {} - {}", mail.to, mail.subject, mail.body);
+        Ok(())
+    }
+}
+
+/// A transport which appends every email to a file.
+pub struct FileTransport {
+    /// The path of the file to write to.
+    pub path: String
+}
+
+#[rocket::async_trait]
+impl MailTransport for FileTransport {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        let content = format!("To: {}\nSubject: {}\n\n{}\n\n", mail.to, mail.subject, mail.body);
+        file.write_all(content.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+/// A transport which sends every email through an SMTP server.
+pub struct SmtpTransport {
+    /// The connection to the SMTP server.
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+
+    /// The sender of every email.
+    from: Mailbox
+}
+
+impl SmtpTransport {
+    /// Creates an SMTP transport from the configuration.
+    pub fn new(config: &MailConfig) -> anyhow::Result<Self> {
+        let builder = if config.smtp_tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+        };
+        let builder = builder.port(config.smtp_port);
+        let builder = match (&config.smtp_username, &config.smtp_password) {
+            (Some(username), Some(password)) => builder.credentials(
+                Credentials::new(username.clone(), password.clone())
+            ),
+            _ => builder
+        };
+
+        Ok(SmtpTransport {
+            transport: builder.build(),
+            from: config.from.parse()?
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl MailTransport for SmtpTransport {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail.to.parse()?)
+            .subject(mail.subject)
+            .body(mail.body)?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Creates the mail transport described by the configuration.
+pub fn transport_for(config: &MailConfig) -> anyhow::Result<Box<dyn MailTransport>> {
+    Ok(match config.transport {
+        MailTransportKind::Log => Box::new(LogTransport),
+        MailTransportKind::File => Box::new(FileTransport { path: config.path.clone() }),
+        MailTransportKind::Smtp => Box::new(SmtpTransport::new(config)?)
+    })
+}
+
+/// Creates a fairing which adds the configured mail transport to Rocket's managed state.
+///
+/// This should be attached after the configuration fairing.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Mail Transport", |rocket| async {
+        let transport = match rocket.state::<Config>() {
+            Some(config) => transport_for(&config.mail),
+            None => Ok(Box::new(LogTransport) as Box<dyn MailTransport>)
+        };
+
+        match transport {
+            Ok(transport) => Ok(rocket.manage(transport)),
+            Err(err) => {
+                error!("Failed to create the mail transport: {}", err);
+                Err(rocket)
+            }
+        }
+    })
+}
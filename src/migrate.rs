@@ -0,0 +1,108 @@
+use anyhow::bail;
+use log::{error, info, warn};
+use rocket::fairing::AdHoc;
+use rocket_db_pools::Database;
+use sqlx::migrate::Migrator;
+
+use crate::{config::Config, db::{DbConnection, DbPool, SDK}};
+
+/// The migrations which create and update the database schema.
+///
+/// These are embedded from the backend's directory in `migrations` when the server is built.
+#[cfg(feature = "mysql")]
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/mysql");
+
+#[cfg(feature = "sqlite")]
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+#[cfg(feature = "postgres")]
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Checks if the migrations table exists.
+#[cfg(feature = "mysql")]
+const MIGRATIONS_TABLE_QUERY: &str =
+    "SELECT COUNT(*) FROM information_schema.tables WHERE `table_schema` = DATABASE() AND `table_name` = '_sqlx_migrations'";
+
+#[cfg(feature = "sqlite")]
+const MIGRATIONS_TABLE_QUERY: &str =
+    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'";
+
+#[cfg(feature = "postgres")]
+const MIGRATIONS_TABLE_QUERY: &str =
+    "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = '_sqlx_migrations'";
+
+/// Returns the version of the newest migration applied to the database.
+///
+/// Returns `None` if the database has never been migrated.
+async fn schema_version(db: &mut DbConnection) -> sqlx::Result<Option<i64>> {
+    // The migrations table doesn't exist until the first migration is applied,
+    // so these queries can't be checked when the server is built.
+    let migrated: i64 = sqlx::query_scalar(MIGRATIONS_TABLE_QUERY)
+        .fetch_one(&mut *db).await?;
+    if migrated == 0 {
+        return Ok(None);
+    }
+
+    sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = TRUE")
+        .fetch_one(&mut *db).await
+}
+
+/// Checks that the database schema isn't newer than this build understands.
+///
+/// Returns how many migrations haven't been applied yet.
+pub async fn check_schema(db: &mut DbConnection) -> anyhow::Result<usize> {
+    let supported = MIGRATOR.iter().map(|migration| migration.version).max().unwrap_or(0);
+    let current = schema_version(db).await?;
+
+    if let Some(current) = current {
+        if current > supported {
+            bail!("the database schema is at version {}, but this build only supports up to version {}", current, supported);
+        }
+    }
+
+    Ok(MIGRATOR.iter().filter(|migration| Some(migration.version) > current).count())
+}
+
+/// Checks the database schema, and applies any pending migrations if `apply` is set.
+async fn migrate(pool: DbPool, apply: bool) -> anyhow::Result<()> {
+    let mut db = pool.acquire().await?;
+
+    let pending = check_schema(&mut db).await?;
+    if pending == 0 {
+        info!("The database schema is up to date");
+        return Ok(());
+    }
+
+    if !apply {
+        warn!("{} database migration(s) are pending, but applying them is disabled", pending);
+        return Ok(());
+    }
+
+    MIGRATOR.run(&mut *db).await?;
+    info!("Applied {} database migration(s)", pending);
+    Ok(())
+}
+
+/// Creates a fairing which checks the database schema, and applies pending migrations.
+///
+/// Launch is aborted if the schema is newer than this build, or a migration fails.
+/// This should be attached after the configuration and database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Database Migrations", |rocket| async {
+        let apply = rocket.state::<Config>().is_none_or(|config| config.migrations.apply);
+
+        // The database fairing reports its own errors.
+        let pool = match SDK::fetch(&rocket) {
+            Some(db) => DbPool::clone(db),
+            None => return Err(rocket)
+        };
+
+        match migrate(pool, apply).await {
+            Ok(()) => Ok(rocket),
+            Err(err) => {
+                error!("Failed to migrate the database: {:#}", err);
+                Err(rocket)
+            }
+        }
+    })
+}
@@ -0,0 +1,70 @@
+use sqlx::Connection;
+
+use crate::{db::{query, query_scalar, DbConnection}, state::{self, AccountState, TransitionError}, utils};
+
+/// Deletes an account, and scrubs its personal data.
+///
+/// The account is moved to `Deleted` in the same transaction, so an account which
+/// left `PendingDelete` in the meantime (such as to `LegalHold`) is left untouched.
+pub async fn purge_account(db: &mut DbConnection, uid: i32, reason: &str) -> Result<(), TransitionError> {
+    let mut tx = db.begin().await?;
+    state::transition(&mut tx, uid, AccountState::Deleted, reason).await?;
+
+    // Mobile login codes are keyed by the number, not the account.
+    let mobile = query_scalar!(
+        "SELECT `mobile` FROM `accounts` WHERE `uid` = ?",
+        "SELECT mobile FROM accounts WHERE uid = $1",
+        uid
+    ).fetch_one(&mut *tx).await?;
+    if let Some(mobile) = mobile {
+        query!(
+            "DELETE FROM `mobile_captchas` WHERE `mobile` = ?",
+            "DELETE FROM mobile_captchas WHERE mobile = $1",
+            mobile
+        ).execute(&mut *tx).await?;
+    }
+
+    // Scrub the account's personal data, but keep the row so the uid isn't reused.
+    query!(
+        "UPDATE `accounts` SET `name` = NULL, `email` = NULL, `mobile` = NULL, `password` = NULL, `guest_device` = NULL, `email_verified` = 0, `epoch_delete` = NULL WHERE `uid` = ?",
+        "UPDATE accounts SET name = NULL, email = NULL, mobile = NULL, password = NULL, guest_device = NULL, email_verified = 0, epoch_delete = NULL WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    // Remove everything else which belongs to the account.
+    query!("DELETE FROM `devices` WHERE `uid` = ?", "DELETE FROM devices WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `login_tokens` WHERE `uid` = ?", "DELETE FROM login_tokens WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `combo_tokens` WHERE `uid` = ?", "DELETE FROM combo_tokens WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `grant_tickets` WHERE `uid` = ?", "DELETE FROM grant_tickets WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `reactivate_tickets` WHERE `uid` = ?", "DELETE FROM reactivate_tickets WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `email_verifications` WHERE `uid` = ?", "DELETE FROM email_verifications WHERE uid = $1", uid).execute(&mut *tx).await?;
+    query!("DELETE FROM `password_resets` WHERE `uid` = ?", "DELETE FROM password_resets WHERE uid = $1", uid).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Purges every account whose deletion grace period has expired.
+///
+/// Only accounts in `PendingDelete` are purged; accounts in `LegalHold` are never selected.
+/// Returns how many accounts were purged.
+pub async fn purge_expired(db: &mut DbConnection) -> sqlx::Result<u32> {
+    let pending = AccountState::PendingDelete as i32;
+    let uids = query_scalar!(
+        "SELECT `uid` FROM `accounts` WHERE `state` = ? AND `epoch_delete` <= ?",
+        r#"SELECT uid AS "uid!: i32" FROM accounts WHERE state = $1 AND epoch_delete <= $2"#,
+        pending, utils::current_time()
+    ).fetch_all(&mut *db).await?;
+
+    let mut purged = 0;
+    for uid in uids {
+        match purge_account(&mut *db, uid, "deletion grace period expired").await {
+            Ok(()) => purged += 1,
+            // The account's state changed since it was selected.
+            Err(TransitionError::NotFound | TransitionError::NotAllowed(..)) => (),
+            Err(TransitionError::Database(err)) => return Err(err)
+        }
+    }
+
+    Ok(purged)
+}
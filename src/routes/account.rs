@@ -1,16 +1,34 @@
+use std::collections::HashMap;
+
+use log::warn;
 use rocket::{form::Form, response::Redirect};
-use rocket::Route;
+use rocket::{Route, State};
 use rocket_db_pools::{sqlx, Connection};
-use sqlx::Error;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::{Connection as _, Error};
 use validator::Validate;
+use crate::config::Config;
+use crate::error::ShieldError;
 use crate::constants;
-use crate::{db::SDK, utils};
+use crate::guards::{csrf::CsrfToken, ip_address::IpAddress, language::Language};
+use crate::lockout::{self, Subject};
+use crate::mail::{Mail, MailTransport};
+use crate::state::{self, AccountState};
+use crate::{db::{query, DbConnection, SDK}, utils};
 
 /// Mounts all routes.
 pub fn mount() -> Vec<Route> {
     routes![
         account_register_page,
-        account_register
+        account_register,
+        account_verify_email,
+        account_forgot_password_page,
+        account_forgot_password,
+        account_reset_password_page,
+        account_reset_password,
+        account_delete_page,
+        account_delete
     ]
 }
 
@@ -29,6 +47,18 @@ enum AccountResponse<'a> {
     #[response(status = 303)]
     Redirect(Redirect),
 
+    /// This should be returned when an HTML page is shown.
+    #[response(status = 200)]
+    Page(Template),
+
+    /// This should be returned when an HTML form has invalid data.
+    #[response(status = 400)]
+    Invalid(Template),
+
+    /// This should be returned when an HTML form couldn't be handled.
+    #[response(status = 500)]
+    Failed(Template),
+
     /// This should be returned if the request was handled successfully.
     #[response(status = 200)]
     Successful(&'a str)
@@ -47,60 +77,185 @@ struct RegisterForm<'v> {
     passwordv1: &'v str,
     
     /// This is used for confirming the first password.
-    passwordv2: &'v str
+    passwordv2: &'v str,
+
+    /// The CSRF token given on the registration page.
+    csrf_token: &'v str
+}
+
+/// Creates an email verification token, and sends it to the account holder.
+async fn send_verification(
+    db: &mut DbConnection,
+    config: &Config,
+    mail: &dyn MailTransport,
+    uid: i32,
+    email: &str
+) -> anyhow::Result<()> {
+    // Generate a new verification token.
+    let token = utils::random_token();
+    let current_time = utils::current_time();
+    query!(
+        "INSERT INTO `email_verifications` (`uid`, `token`, `epoch_created`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `token` = ?, `epoch_created` = ?",
+        "INSERT INTO email_verifications (uid, token, epoch_created) VALUES ($1, $2, $3) ON CONFLICT (uid) DO UPDATE SET token = $4, epoch_created = $5",
+        uid, token, current_time, token, current_time
+    ).execute(&mut *db).await?;
+
+    // Send the verification link to the account holder.
+    let link = format!(
+        "{}/account/verify_email?uid={}&token={}",
+        config.mail.base_url, uid, token
+    );
+    mail.send(Mail {
+        to: email.to_string(),
+        subject: constants::SUBJECT_VERIFY_EMAIL,
+        body: format!("Please verify your email address by opening this link: {}", link)
+    }).await
+}
+
+/// Values shown on the registration page.
+#[derive(Serialize, Default)]
+struct RegisterPage<'a> {
+    /// The language of the page.
+    lang: &'static str,
+
+    /// The request type, which is kept when the form is submitted.
+    r#type: Option<&'a str>,
+
+    /// The CSRF token, which is sent back with the form.
+    csrf_token: String,
+
+    /// The username entered by the user.
+    username: &'a str,
+
+    /// The email address entered by the user.
+    email: &'a str,
+
+    /// Error messages for individual fields, keyed by the field's name.
+    errors: HashMap<&'static str, &'static str>,
+
+    /// An error message for the whole form.
+    error: Option<&'static str>,
+
+    /// A message shown once the account is created.
+    success: Option<&'static str>
 }
 
 /// Handles sending the account registration HTML to the client.
-#[get("/register")]
-fn account_register_page() -> &'static str {
-    "page"
+#[get("/register?<type>")]
+fn account_register_page(
+    r#type: Option<&'_ str>,
+    csrf_token: CsrfToken,
+    language: Language
+) -> Template {
+    Template::render("register", RegisterPage {
+        lang: language.0,
+        r#type,
+        csrf_token: csrf_token.0,
+        ..Default::default()
+    })
 }
 
 /// Handles registering an account internally.
 ///
 /// See the `RegisterForm` struct for the form data.
+/// On failure, the page is shown again with the entered values and error messages.
 #[post("/register?<type>", data = "<form>")]
+#[allow(clippy::too_many_arguments)]
 async fn account_register<'a>(
     mut db: Connection<SDK>,
+    config: &State<Config>,
+    mail: &State<Box<dyn MailTransport>>,
     r#type: Option<&'_ str>,
-    form: Form<RegisterForm<'_>>
+    form: Form<RegisterForm<'_>>,
+    csrf_token: CsrfToken,
+    language: Language
 ) -> AccountResponse<'a> {
-    // Check if the user already exists.
-    match sqlx::query!(
-        "SELECT * from `accounts` where `name` = ? OR `email` = ?",
-        form.username, form.email
-    ).fetch_one(&mut **db).await {
-        Err(Error::RowNotFound) => (),
-        Ok(_) => return AccountResponse::BadRequest(constants::MESSAGE_EXISTING_USER),
-        _ => return AccountResponse::ServerError(constants::MESSAGE_SERVER_ERROR)
+    let mut page = RegisterPage {
+        lang: language.0,
+        r#type,
+        csrf_token: csrf_token.0.clone(),
+        username: form.username,
+        email: form.email,
+        ..Default::default()
+    };
+
+    // Check that the form came from the registration page.
+    if !csrf_token.verify(form.csrf_token) {
+        page.error = Some(language.translate(constants::MESSAGE_INVALID_CSRF));
+        return AccountResponse::Invalid(Template::render("register", page));
     }
 
     // Validate the user provided data.
-    match form.validate() {
-        Ok(_) => (),
-        Err(_) => return AccountResponse::BadRequest(constants::MESSAGE_INVALID_FORM),
+    if let Err(errors) = form.validate() {
+        for field in errors.field_errors().keys() {
+            let (field, message) = match field.as_ref() {
+                "username" => ("username", constants::MESSAGE_INVALID_USERNAME),
+                "email" => ("email", constants::MESSAGE_INVALID_EMAIL),
+                _ => ("passwordv1", constants::MESSAGE_INVALID_PASSWORD)
+            };
+            page.errors.insert(field, language.translate(message));
+        }
     }
 
     // Check if the passwords match.
     let password = form.passwordv1.trim();
     if password != form.passwordv2 {
-        return AccountResponse::BadRequest(constants::MESSAGE_MISMATCH_PASSWORD);
+        page.errors.insert("passwordv2", language.translate(constants::MESSAGE_MISMATCH_PASSWORD));
+    }
+
+    if !page.errors.is_empty() {
+        return AccountResponse::Invalid(Template::render("register", page));
+    }
+
+    // Check if the user already exists.
+    match query!(
+        "SELECT * from `accounts` where `name` = ? OR `email` = ?",
+        "SELECT * from accounts where name = $1 OR email = $2",
+        form.username, form.email
+    ).fetch_one(&mut **db).await {
+        Err(Error::RowNotFound) => (),
+        Ok(_) => {
+            page.error = Some(language.translate(constants::MESSAGE_EXISTING_USER));
+            return AccountResponse::Invalid(Template::render("register", page));
+        },
+        _ => {
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            return AccountResponse::Failed(Template::render("register", page));
+        }
     }
 
     // Hash the password for storage in the database.
-    let hashed = match utils::hash_password(password) {
-        Ok(hash) => hash,
-        Err(_) => return AccountResponse::ServerError(constants::MESSAGE_SERVER_ERROR)
+    let Ok(hashed) = utils::hash_password(password) else {
+        page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+        return AccountResponse::Failed(Template::render("register", page));
     };
 
     // Insert the user into the database.
-    let Ok(_) = sqlx::query!(
+    let current_time = utils::current_time();
+
+    #[cfg(feature = "mysql")]
+    let result = sqlx::query!(
         "INSERT INTO `accounts` (`name`, `email`, `password`, `epoch_created`) VALUES (?, ?, ?, ?)",
-        form.username, form.email, hashed, utils::current_time()
-    ).execute(&mut **db).await else {
-        return AccountResponse::ServerError(constants::MESSAGE_SERVER_ERROR);
+        form.username, form.email, hashed, current_time
+    ).execute(&mut **db).await.map(|result| result.last_insert_id() as i32);
+
+    #[cfg(not(feature = "mysql"))]
+    let result = sqlx::query_scalar!(
+        r#"INSERT INTO accounts (name, email, password, epoch_created) VALUES ($1, $2, $3, $4) RETURNING uid AS "uid!: i32""#,
+        form.username, form.email, hashed, current_time
+    ).fetch_one(&mut **db).await;
+
+    let Ok(uid) = result else {
+        page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+        return AccountResponse::Failed(Template::render("register", page));
     };
 
+    // Send the email verification link.
+    // The account is still usable if this fails.
+    if let Err(error) = send_verification(&mut **db, config, &***mail, uid, form.email).await {
+        warn!("Failed to send a verification email to {}: {}", uid, error);
+    }
+
     // If the type is `sdk`, redirect the user.
     if let Some(r#type) = r#type {
         if r#type == constants::WEBVIEW_REQUEST_TYPE_SDK {
@@ -116,5 +271,505 @@ async fn account_register<'a>(
         }
     }
 
-    AccountResponse::Successful(constants::MESSAGE_ACCOUNT_CREATED)
-}
\ No newline at end of file
+    page.success = Some(language.translate(constants::MESSAGE_ACCOUNT_CREATED));
+    AccountResponse::Page(Template::render("register", page))
+}
+
+/// Handles verifying an account's email address.
+///
+/// This is opened from the link sent in `send_verification`.
+#[get("/verify_email?<uid>&<token>")]
+async fn account_verify_email<'a>(
+    mut db: Connection<SDK>,
+    uid: i32,
+    token: &'_ str,
+    language: Language
+) -> AccountResponse<'a> {
+    // Check the verification token.
+    match query!(
+        "SELECT * FROM `email_verifications` WHERE `uid` = ? AND `token` = ?",
+        "SELECT * FROM email_verifications WHERE uid = $1 AND token = $2",
+        uid, token
+    ).fetch_one(&mut **db).await {
+        Ok(entry) => {
+            if entry.epoch_created + constants::EMAIL_VERIFY_LIFETIME < utils::current_time() {
+                return AccountResponse::BadRequest(language.translate(constants::MESSAGE_BAD_VERIFY_LINK));
+            }
+        },
+        Err(Error::RowNotFound) => return AccountResponse::BadRequest(language.translate(constants::MESSAGE_BAD_VERIFY_LINK)),
+        Err(_) => return AccountResponse::ServerError(language.translate(constants::MESSAGE_SERVER_ERROR))
+    }
+
+    // Mark the email address as verified.
+    let Ok(_) = query!(
+        "UPDATE `accounts` SET `email_verified` = 1 WHERE `uid` = ?",
+        "UPDATE accounts SET email_verified = 1 WHERE uid = $1",
+        uid
+    ).execute(&mut **db).await else {
+        return AccountResponse::ServerError(language.translate(constants::MESSAGE_SERVER_ERROR));
+    };
+
+    // The token has been used, so it can be removed.
+    query!(
+        "DELETE FROM `email_verifications` WHERE `uid` = ?",
+        "DELETE FROM email_verifications WHERE uid = $1",
+        uid
+    ).execute(&mut **db).await.ok();
+
+    AccountResponse::Successful(language.translate(constants::MESSAGE_EMAIL_VERIFIED))
+}
+
+/// Values shown on the forgot password page.
+#[derive(Serialize, Default)]
+struct ForgotPasswordPage<'a> {
+    lang: &'static str,
+    csrf_token: String,
+    email: &'a str,
+    errors: HashMap<&'static str, &'static str>,
+    error: Option<&'static str>,
+    success: Option<&'static str>
+}
+
+/// Form data sent by the client when requesting a password reset.
+#[derive(Debug, Validate, FromForm)]
+struct ForgotPasswordForm<'v> {
+    #[validate(email, length(max = 128))]
+    email: &'v str,
+
+    /// The CSRF token given on the forgot password page.
+    csrf_token: &'v str
+}
+
+/// Creates a password reset token, and sends it to the account holder.
+///
+/// Only a hash of the token is stored, and any previous token is replaced.
+async fn send_password_reset(
+    db: &mut DbConnection,
+    config: &Config,
+    mail: &dyn MailTransport,
+    uid: i32,
+    email: &str
+) -> anyhow::Result<()> {
+    // Generate a new reset token.
+    let token = utils::random_token();
+    let token_hash = utils::hash_token(&token);
+    let current_time = utils::current_time();
+    query!(
+        "INSERT INTO `password_resets` (`uid`, `token_hash`, `epoch_created`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `token_hash` = ?, `epoch_created` = ?",
+        "INSERT INTO password_resets (uid, token_hash, epoch_created) VALUES ($1, $2, $3) ON CONFLICT (uid) DO UPDATE SET token_hash = $4, epoch_created = $5",
+        uid, token_hash, current_time, token_hash, current_time
+    ).execute(&mut *db).await?;
+
+    // Send the reset link to the account holder.
+    let link = format!(
+        "{}/account/reset_password?uid={}&token={}",
+        config.mail.base_url, uid, token
+    );
+    mail.send(Mail {
+        to: email.to_string(),
+        subject: constants::SUBJECT_RESET_PASSWORD,
+        body: format!("Reset your password by opening this link: {}\n\nIf you didn't request this, you can ignore this email.", link)
+    }).await
+}
+
+/// Handles sending the forgot password HTML to the client.
+#[get("/forgot_password")]
+fn account_forgot_password_page(csrf_token: CsrfToken, language: Language) -> Template {
+    Template::render("forgot_password", ForgotPasswordPage {
+        lang: language.0,
+        csrf_token: csrf_token.0,
+        ..Default::default()
+    })
+}
+
+/// Handles a password reset request.
+///
+/// The same message is shown whether or not the account exists, so accounts can't be enumerated.
+#[post("/forgot_password", data = "<form>")]
+async fn account_forgot_password<'a>(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    mail: &State<Box<dyn MailTransport>>,
+    form: Form<ForgotPasswordForm<'_>>,
+    csrf_token: CsrfToken,
+    language: Language
+) -> AccountResponse<'a> {
+    let mut page = ForgotPasswordPage {
+        lang: language.0,
+        csrf_token: csrf_token.0.clone(),
+        email: form.email,
+        ..Default::default()
+    };
+
+    // Check that the form came from the forgot password page.
+    if !csrf_token.verify(form.csrf_token) {
+        page.error = Some(language.translate(constants::MESSAGE_INVALID_CSRF));
+        return AccountResponse::Invalid(Template::render("forgot_password", page));
+    }
+
+    // Validate the user provided data.
+    if form.validate().is_err() {
+        page.errors.insert("email", language.translate(constants::MESSAGE_INVALID_EMAIL));
+        return AccountResponse::Invalid(Template::render("forgot_password", page));
+    }
+
+    // Find the account which uses the email address.
+    let account = match query!(
+        "SELECT `uid`, `state` FROM `accounts` WHERE `email` = ?",
+        r#"SELECT uid AS "uid!: i32", state FROM accounts WHERE email = $1"#,
+        form.email
+    ).fetch_optional(&mut **db).await {
+        Ok(account) => account,
+        Err(_) => {
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            return AccountResponse::Failed(Template::render("forgot_password", page));
+        }
+    };
+
+    // Only send a link if the account can log in.
+    if let Some(account) = account {
+        if AccountState::try_from(account.state).is_ok_and(AccountState::can_login) {
+            if let Err(error) = send_password_reset(&mut **db, config, &***mail, account.uid, form.email).await {
+                warn!("Failed to send a password reset email to {}: {}", account.uid, error);
+            }
+        }
+    }
+
+    page.success = Some(language.translate(constants::MESSAGE_RESET_SENT));
+    AccountResponse::Page(Template::render("forgot_password", page))
+}
+
+/// Values shown on the reset password page.
+#[derive(Serialize, Default)]
+struct ResetPasswordPage<'a> {
+    lang: &'static str,
+    csrf_token: String,
+    uid: i32,
+
+    /// The reset token from the link.
+    ///
+    /// This is empty if the link is invalid, which hides the form.
+    token: &'a str,
+
+    errors: HashMap<&'static str, &'static str>,
+    error: Option<&'static str>,
+    success: Option<&'static str>
+}
+
+/// Form data sent by the client when resetting a password.
+#[derive(Debug, Validate, FromForm)]
+struct ResetPasswordForm<'v> {
+    /// The account's unique ID, from the reset link.
+    uid: i32,
+
+    /// The reset token, from the reset link.
+    token: &'v str,
+
+    #[validate(length(min = 8, max = 128))]
+    passwordv1: &'v str,
+
+    /// This is used for confirming the first password.
+    passwordv2: &'v str,
+
+    /// The CSRF token given on the reset password page.
+    csrf_token: &'v str
+}
+
+/// Checks that a password reset token is valid for the account.
+async fn check_reset_token(db: &mut DbConnection, uid: i32, token: &str) -> sqlx::Result<bool> {
+    let entry = query!(
+        "SELECT `epoch_created` FROM `password_resets` WHERE `uid` = ? AND `token_hash` = ?",
+        "SELECT epoch_created FROM password_resets WHERE uid = $1 AND token_hash = $2",
+        uid, utils::hash_token(token)
+    ).fetch_optional(&mut *db).await?;
+
+    Ok(entry.is_some_and(|entry| {
+        entry.epoch_created + constants::PASSWORD_RESET_LIFETIME >= utils::current_time()
+    }))
+}
+
+/// Handles sending the reset password HTML to the client.
+///
+/// This is opened from the link sent in `send_password_reset`.
+#[get("/reset_password?<uid>&<token>")]
+async fn account_reset_password_page<'a>(
+    mut db: Connection<SDK>,
+    uid: i32,
+    token: &'_ str,
+    csrf_token: CsrfToken,
+    language: Language
+) -> AccountResponse<'a> {
+    let mut page = ResetPasswordPage {
+        lang: language.0,
+        csrf_token: csrf_token.0,
+        uid,
+        token,
+        ..Default::default()
+    };
+
+    match check_reset_token(&mut **db, uid, token).await {
+        Ok(true) => AccountResponse::Page(Template::render("reset_password", page)),
+        Ok(false) => {
+            page.token = "";
+            page.error = Some(language.translate(constants::MESSAGE_BAD_RESET_LINK));
+            AccountResponse::Invalid(Template::render("reset_password", page))
+        },
+        Err(_) => {
+            page.token = "";
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            AccountResponse::Failed(Template::render("reset_password", page))
+        }
+    }
+}
+
+/// Handles resetting an account's password.
+///
+/// The reset token can only be used once, and every login token for the account is revoked.
+#[post("/reset_password", data = "<form>")]
+async fn account_reset_password<'a>(
+    mut db: Connection<SDK>,
+    form: Form<ResetPasswordForm<'_>>,
+    csrf_token: CsrfToken,
+    language: Language
+) -> AccountResponse<'a> {
+    let mut page = ResetPasswordPage {
+        lang: language.0,
+        csrf_token: csrf_token.0.clone(),
+        uid: form.uid,
+        token: form.token,
+        ..Default::default()
+    };
+
+    // Check that the form came from the reset password page.
+    if !csrf_token.verify(form.csrf_token) {
+        page.error = Some(language.translate(constants::MESSAGE_INVALID_CSRF));
+        return AccountResponse::Invalid(Template::render("reset_password", page));
+    }
+
+    // Check the reset token.
+    match check_reset_token(&mut **db, form.uid, form.token).await {
+        Ok(true) => (),
+        Ok(false) => {
+            page.token = "";
+            page.error = Some(language.translate(constants::MESSAGE_BAD_RESET_LINK));
+            return AccountResponse::Invalid(Template::render("reset_password", page));
+        },
+        Err(_) => {
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            return AccountResponse::Failed(Template::render("reset_password", page));
+        }
+    }
+
+    // Validate the new password.
+    let password = form.passwordv1.trim();
+    if form.validate().is_err() {
+        page.errors.insert("passwordv1", language.translate(constants::MESSAGE_INVALID_PASSWORD));
+    }
+    if password != form.passwordv2 {
+        page.errors.insert("passwordv2", language.translate(constants::MESSAGE_MISMATCH_PASSWORD));
+    }
+    if !page.errors.is_empty() {
+        return AccountResponse::Invalid(Template::render("reset_password", page));
+    }
+
+    // Hash the password for storage in the database.
+    let Ok(hashed) = utils::hash_password(password) else {
+        page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+        return AccountResponse::Failed(Template::render("reset_password", page));
+    };
+
+    // Update the password, use up the reset token, and log out every device.
+    match reset_password(&mut **db, form.uid, form.token, &hashed).await {
+        Ok(true) => (),
+        Ok(false) => {
+            // The token was used by another request in the meantime.
+            page.token = "";
+            page.error = Some(language.translate(constants::MESSAGE_BAD_RESET_LINK));
+            return AccountResponse::Invalid(Template::render("reset_password", page));
+        },
+        Err(error) => {
+            warn!("Failed to reset the password of {}: {}", form.uid, error);
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            return AccountResponse::Failed(Template::render("reset_password", page));
+        }
+    }
+
+    page.token = "";
+    page.success = Some(language.translate(constants::MESSAGE_PASSWORD_RESET));
+    AccountResponse::Page(Template::render("reset_password", page))
+}
+
+/// Replaces an account's password in a single transaction.
+///
+/// This uses up the reset token, and revokes every login and combo token for the account.
+/// Returns `false` if the reset token was already used.
+async fn reset_password(db: &mut DbConnection, uid: i32, token: &str, hashed: &str) -> sqlx::Result<bool> {
+    let mut tx = db.begin().await?;
+
+    // Deleting the token first means only one request can use it.
+    let result = query!(
+        "DELETE FROM `password_resets` WHERE `uid` = ? AND `token_hash` = ?",
+        "DELETE FROM password_resets WHERE uid = $1 AND token_hash = $2",
+        uid, utils::hash_token(token)
+    ).execute(&mut *tx).await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    query!(
+        "UPDATE `accounts` SET `password` = ? WHERE `uid` = ?",
+        "UPDATE accounts SET password = $1 WHERE uid = $2",
+        hashed, uid
+    ).execute(&mut *tx).await?;
+
+    query!(
+        "DELETE FROM `login_tokens` WHERE `uid` = ?",
+        "DELETE FROM login_tokens WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    query!(
+        "DELETE FROM `combo_tokens` WHERE `uid` = ?",
+        "DELETE FROM combo_tokens WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Values shown on the account deletion page.
+#[derive(Serialize, Default)]
+struct DeleteAccountPage<'a> {
+    lang: &'static str,
+    csrf_token: String,
+    account: &'a str,
+    error: Option<&'static str>,
+    success: Option<&'static str>,
+
+    /// When the account will be deleted, as a UNIX timestamp.
+    deletion_date: Option<i64>
+}
+
+/// Form data sent by the client when requesting account deletion.
+#[derive(Debug, FromForm)]
+struct DeleteAccountForm<'v> {
+    /// The username or email address of the account.
+    account: &'v str,
+
+    /// The account's password.
+    password: &'v str,
+
+    /// The CSRF token given on the account deletion page.
+    csrf_token: &'v str
+}
+
+/// Handles sending the account deletion HTML to the client.
+#[get("/delete")]
+fn account_delete_page(csrf_token: CsrfToken, language: Language) -> Template {
+    Template::render("delete_account", DeleteAccountPage {
+        lang: language.0,
+        csrf_token: csrf_token.0,
+        ..Default::default()
+    })
+}
+
+/// Handles a request to delete an account.
+///
+/// The account is moved to `PendingDelete`, and is purged once the grace period ends.
+/// Logging in before then offers to reactivate the account.
+#[post("/delete", data = "<form>")]
+async fn account_delete<'a>(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    form: Form<DeleteAccountForm<'_>>,
+    csrf_token: CsrfToken,
+    ip_address: IpAddress,
+    language: Language
+) -> AccountResponse<'a> {
+    let mut page = DeleteAccountPage {
+        lang: language.0,
+        csrf_token: csrf_token.0.clone(),
+        account: form.account,
+        ..Default::default()
+    };
+
+    // Check that the form came from the account deletion page.
+    if !csrf_token.verify(form.csrf_token) {
+        page.error = Some(language.translate(constants::MESSAGE_INVALID_CSRF));
+        return AccountResponse::Invalid(Template::render("delete_account", page));
+    }
+
+    match request_deletion(&mut **db, config, &form, &ip_address.0).await {
+        Ok(deletion_date) => {
+            page.success = Some(language.translate(constants::MESSAGE_DELETION_SCHEDULED));
+            page.deletion_date = Some(deletion_date);
+            AccountResponse::Page(Template::render("delete_account", page))
+        },
+        Err(ShieldError::System) => {
+            page.error = Some(language.translate(constants::MESSAGE_SERVER_ERROR));
+            AccountResponse::Failed(Template::render("delete_account", page))
+        },
+        Err(error) => {
+            page.error = Some(language.translate(error.message()));
+            AccountResponse::Invalid(Template::render("delete_account", page))
+        }
+    }
+}
+
+/// Checks the account holder's credentials, and schedules the account for deletion.
+///
+/// Failed attempts count towards the login lockout.
+/// Returns when the account will be deleted.
+async fn request_deletion(
+    db: &mut DbConnection,
+    config: &Config,
+    form: &DeleteAccountForm<'_>,
+    ip_address: &str
+) -> Result<i64, ShieldError> {
+    // Check if the client's address is locked out.
+    let address = Subject::Address(ip_address);
+    if lockout::is_locked(&mut *db, &[address]).await? {
+        return Err(ShieldError::LockedOut);
+    }
+
+    // Find the account.
+    let Some(account) = query!(
+        "SELECT `uid`, `password`, `state` FROM `accounts` WHERE `name` = ? OR `email` = ?",
+        r#"SELECT uid AS "uid!: i32", password, state FROM accounts WHERE name = $1 OR email = $2"#,
+        form.account, form.account
+    ).fetch_optional(&mut *db).await? else {
+        lockout::record_failure(&mut *db, &config.lockout, &[address]).await?;
+        return Err(ShieldError::AccountNotFound);
+    };
+
+    let subjects = [Subject::Account(account.uid), address];
+    if lockout::is_locked(&mut *db, &subjects).await? {
+        return Err(ShieldError::LockedOut);
+    }
+
+    // Accounts without a password can't be deleted from here.
+    if !account.password.is_some_and(|hashed| utils::verify_password(form.password, &hashed)) {
+        lockout::record_failure(&mut *db, &config.lockout, &subjects).await?;
+        return Err(ShieldError::WrongPassword);
+    }
+    lockout::clear(&mut *db, Subject::Account(account.uid)).await?;
+
+    // Only active accounts can be scheduled for deletion.
+    if account.state != AccountState::Active {
+        return Err(ShieldError::AccountBanned);
+    }
+
+    // Move the account to `PendingDelete`, and record when it should be purged.
+    let deletion_date = utils::current_time() + i64::from(config.accounts.deletion_grace);
+    let mut tx = db.begin().await?;
+    state::transition(&mut tx, account.uid, AccountState::PendingDelete, "deletion requested by user").await?;
+    query!(
+        "UPDATE `accounts` SET `epoch_delete` = ? WHERE `uid` = ?",
+        "UPDATE accounts SET epoch_delete = $1 WHERE uid = $2",
+        deletion_date, account.uid
+    ).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(deletion_date)
+}
@@ -0,0 +1,235 @@
+use log::info;
+use rocket::{http::Status, serde::json::Json, Catcher, Request, Route, State};
+use rocket_db_pools::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::admin::{self, AccountDetails, AccountState, AccountSummary, DeviceInfo, TokenInfo};
+use crate::{config::Config, db::SDK, error::{AdminError, AdminResult}, guards::admin_token::AdminToken};
+
+/// Mounts all routes.
+///
+/// Every handler takes its `AdminToken` before its database connection,
+/// so unauthenticated requests are rejected without taking a connection from the pool.
+pub fn mount() -> Vec<Route> {
+    routes![
+        search_accounts,
+        get_account,
+        set_state,
+        set_password,
+        clear_lockout,
+        clear_address_lockout,
+        list_devices,
+        revoke_device,
+        list_tokens,
+        revoke_tokens,
+        revoke_token,
+        clear_tickets
+    ]
+}
+
+/// Returns catchers which send errors as JSON, like the rest of the API.
+pub fn catchers() -> Vec<Catcher> {
+    catchers![default_catcher]
+}
+
+#[catch(default)]
+fn default_catcher(status: Status, _request: &Request<'_>) -> (Status, Json<Value>) {
+    (status, Json(json!({ "error": status.reason().unwrap_or("Unknown error.") })))
+}
+
+/// How many rows were removed by a request.
+#[derive(Serialize)]
+struct Revoked {
+    revoked: u64
+}
+
+#[derive(Deserialize)]
+struct StateRequest {
+    /// The state to move the account to.
+    ///
+    /// See `AccountState` for the values.
+    state: i32,
+
+    /// Why the state is being changed, recorded in the account's state history.
+    reason: String
+}
+
+#[derive(Deserialize)]
+struct PasswordRequest {
+    password: String
+}
+
+/// Searches for accounts by their ID, or the start of their username or email.
+///
+/// Every given filter must match.
+#[get("/accounts?<uid>&<name>&<email>")]
+async fn search_accounts(
+    _token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: Option<i32>,
+    name: Option<&str>,
+    email: Option<&str>
+) -> AdminResult<Vec<AccountSummary>> {
+    Ok(Json(admin::search_accounts(&mut **db, uid, name, email).await?))
+}
+
+/// Shows an account's details, including its recent state changes.
+#[get("/accounts/<uid>")]
+async fn get_account(
+    _token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<AccountDetails> {
+    Ok(Json(admin::get_account(&mut **db, uid).await?))
+}
+
+/// Moves an account to a new state.
+///
+/// See `admin::set_state` for how deletion is handled.
+#[put("/accounts/<uid>/state", data = "<body>")]
+async fn set_state(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    uid: i32,
+    body: Json<StateRequest>
+) -> AdminResult<AccountSummary> {
+    let to = AccountState::try_from(body.state)
+        .map_err(|_| AdminError::BadRequest("Unknown account state."))?;
+    if body.reason.trim().is_empty() {
+        return Err(AdminError::BadRequest("A reason is required."));
+    }
+
+    // Record who made the change in the account's history.
+    let reason = format!("{} (by {})", body.reason.trim(), token.name);
+    let account = admin::set_state(&mut **db, uid, to, &reason, config.accounts.deletion_grace).await?;
+
+    info!("Admin '{}' moved account {} to {:?}", token.name, uid, to);
+    Ok(Json(account))
+}
+
+/// Sets an account's password.
+///
+/// Every login and combo token is revoked, so the account must log in again.
+#[put("/accounts/<uid>/password", data = "<body>")]
+async fn set_password(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32,
+    body: Json<PasswordRequest>
+) -> AdminResult<Revoked> {
+    let revoked = admin::set_password(&mut **db, uid, &body.password).await?;
+
+    info!("Admin '{}' set the password of account {}", token.name, uid);
+    Ok(Json(Revoked { revoked }))
+}
+
+/// Clears an account's failed logins, and unlocks it.
+#[delete("/accounts/<uid>/lockout")]
+async fn clear_lockout(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<Value> {
+    admin::clear_lockout(&mut **db, uid).await?;
+
+    info!("Admin '{}' unlocked account {}", token.name, uid);
+    Ok(Json(json!({})))
+}
+
+/// Clears an IP address's failed logins and sent codes, and unlocks it.
+#[delete("/lockouts/<address>")]
+async fn clear_address_lockout(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    address: &str
+) -> AdminResult<Value> {
+    admin::clear_address_lockout(&mut **db, address).await?;
+
+    info!("Admin '{}' unlocked address {}", token.name, address);
+    Ok(Json(json!({})))
+}
+
+/// Lists the devices which can log in to an account without a grant.
+#[get("/accounts/<uid>/devices")]
+async fn list_devices(
+    _token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<Vec<DeviceInfo>> {
+    Ok(Json(admin::list_devices(&mut **db, uid).await?))
+}
+
+/// Removes a device from an account, along with its tokens.
+///
+/// The device needs a new grant the next time it logs in.
+#[delete("/accounts/<uid>/devices/<device>")]
+async fn revoke_device(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32,
+    device: &str
+) -> AdminResult<Value> {
+    admin::revoke_device(&mut **db, uid, device).await?;
+
+    info!("Admin '{}' removed device {} from account {}", token.name, device, uid);
+    Ok(Json(json!({})))
+}
+
+/// Lists an account's login tokens.
+///
+/// The tokens themselves are never shown.
+#[get("/accounts/<uid>/tokens")]
+async fn list_tokens(
+    _token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<Vec<TokenInfo>> {
+    Ok(Json(admin::list_tokens(&mut **db, uid).await?))
+}
+
+/// Revokes every login and combo token of an account.
+#[delete("/accounts/<uid>/tokens")]
+async fn revoke_tokens(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<Revoked> {
+    let revoked = admin::revoke_tokens(&mut **db, uid, None).await?;
+
+    info!("Admin '{}' revoked the tokens of account {}", token.name, uid);
+    Ok(Json(Revoked { revoked }))
+}
+
+/// Revokes the login and combo tokens issued to one of an account's devices.
+#[delete("/accounts/<uid>/tokens/<device>")]
+async fn revoke_token(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32,
+    device: &str
+) -> AdminResult<Revoked> {
+    let revoked = admin::revoke_tokens(&mut **db, uid, Some(device)).await?;
+    if revoked == 0 {
+        return Err(AdminError::NotFound);
+    }
+
+    info!("Admin '{}' revoked the tokens of device {} on account {}", token.name, device, uid);
+    Ok(Json(Revoked { revoked }))
+}
+
+/// Removes every pending grant ticket of an account.
+///
+/// Devices waiting for a grant must log in again to get a new ticket.
+#[delete("/accounts/<uid>/tickets")]
+async fn clear_tickets(
+    token: AdminToken,
+    mut db: Connection<SDK>,
+    uid: i32
+) -> AdminResult<Revoked> {
+    let revoked = admin::clear_tickets(&mut **db, uid).await?;
+
+    info!("Admin '{}' cleared the grant tickets of account {}", token.name, uid);
+    Ok(Json(Revoked { revoked }))
+}
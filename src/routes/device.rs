@@ -0,0 +1,162 @@
+use log::warn;
+use rocket::{serde::json::Json, Route, State};
+use rocket_db_pools::Connection;
+use serde::Deserialize;
+
+use crate::{config::Config, constants, db::{query, SDK}, error::{ShieldError, ShieldResult}, guards::language::Language, lockout::{self, Subject}, mail::{Mail, MailTransport}, state::AccountState, utils};
+
+/// Mounts all routes.
+pub fn mount() -> Vec<Route> {
+    routes![
+        pre_grant_by_ticket,
+        grant_by_ticket
+    ]
+}
+
+#[derive(Deserialize)]
+struct PreGrantRequest {
+    /// The grant ticket given in the login response.
+    action_ticket: String,
+
+    /// The device which is requesting the grant.
+    device: String
+}
+
+/// Sends a verification code for a grant ticket to the account holder.
+#[post("/api/preGrantByTicket", data = "<body>")]
+async fn pre_grant_by_ticket(
+    mut db: Connection<SDK>,
+    mail: &State<Box<dyn MailTransport>>,
+    body: Json<PreGrantRequest>,
+    language: Language
+) -> ShieldResult {
+    // Find the unexpired grant ticket for the device.
+    let current_time = utils::current_time();
+    let ticket = query!(
+        "SELECT * FROM `grant_tickets` WHERE `ticket` = ? AND `device` = ? AND `epoch_created` > ?",
+        "SELECT * FROM grant_tickets WHERE ticket = $1 AND device = $2 AND epoch_created > $3",
+        body.action_ticket, body.device, current_time - constants::TICKET_LIFETIME
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidTicket)?;
+
+    // Tickets which have run out of attempts can't be sent another code.
+    if ticket.attempts as u32 >= constants::GRANT_MAX_ATTEMPTS {
+        return Err(ShieldError::InvalidTicket);
+    }
+
+    // Check that a code wasn't sent too recently.
+    if ticket.epoch_sent.is_some_and(|epoch_sent| epoch_sent + constants::GRANT_RESEND_INTERVAL > current_time) {
+        return Err(ShieldError::RateLimited);
+    }
+
+    // Find the account, and where the code should be sent.
+    let account = query!(
+        "SELECT `email`, `state` FROM `accounts` WHERE `uid` = ?",
+        "SELECT email, state FROM accounts WHERE uid = $1",
+        ticket.uid
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::AccountNotFound)?;
+
+    // Check that devices can be added to the account.
+    if !AccountState::try_from(account.state).is_ok_and(AccountState::can_register) {
+        return Err(ShieldError::AccountBanned);
+    }
+    let email = account.email.ok_or(ShieldError::System)?;
+
+    // Generate a new verification code.
+    let code = utils::random_code();
+    query!(
+        "UPDATE `grant_tickets` SET `code` = ?, `epoch_sent` = ? WHERE `ticket` = ?",
+        "UPDATE grant_tickets SET code = $1, epoch_sent = $2 WHERE ticket = $3",
+        code, current_time, ticket.ticket
+    ).execute(&mut **db).await?;
+
+    // Send the code to the account holder.
+    let message = Mail {
+        to: email,
+        subject: constants::SUBJECT_GRANT_CODE,
+        body: format!("Your verification code is {}.", code)
+    };
+    if let Err(error) = mail.send(message).await {
+        warn!("Failed to send a grant code to {}: {}", ticket.uid, error);
+        return Err(ShieldError::System);
+    }
+
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, ()))
+}
+
+#[derive(Deserialize)]
+struct GrantRequest {
+    /// The verification code sent in `pre_grant_by_ticket`.
+    code: String,
+
+    /// The grant ticket given in the login response.
+    ticket: String
+}
+
+/// Checks a verification code, and grants the device access to the account.
+#[post("/api/grantByTicket", data = "<body>")]
+async fn grant_by_ticket(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    body: Json<GrantRequest>,
+    language: Language
+) -> ShieldResult {
+    // Find the unexpired grant ticket.
+    let ticket = query!(
+        "SELECT * FROM `grant_tickets` WHERE `ticket` = ? AND `epoch_created` > ?",
+        "SELECT * FROM grant_tickets WHERE ticket = $1 AND epoch_created > $2",
+        body.ticket, utils::current_time() - constants::TICKET_LIFETIME
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidTicket)?;
+
+    // Check that a code was sent for this ticket.
+    let (Some(code), Some(epoch_sent)) = (ticket.code, ticket.epoch_sent) else {
+        return Err(ShieldError::InvalidCode);
+    };
+
+    // Check that the code hasn't expired.
+    if epoch_sent + constants::GRANT_CODE_LIFETIME < utils::current_time() {
+        return Err(ShieldError::ExpiredCode);
+    }
+
+    // Check if the account is locked out.
+    let account = Subject::Account(ticket.uid);
+    if lockout::is_locked(&mut **db, &[account]).await? {
+        return Err(ShieldError::LockedOut);
+    }
+
+    // Use up an attempt before comparing, so concurrent guesses can't exceed the limit.
+    let claimed = query!(
+        "UPDATE `grant_tickets` SET `attempts` = `attempts` + 1 WHERE `ticket` = ? AND `attempts` < ?",
+        "UPDATE grant_tickets SET attempts = attempts + 1 WHERE ticket = $1 AND attempts < $2",
+        ticket.ticket, constants::GRANT_MAX_ATTEMPTS as i32
+    ).execute(&mut **db).await?.rows_affected();
+
+    // Compare the code to the one which was sent.
+    // A ticket which has run out of attempts is kept until it expires,
+    // so logging in again doesn't give it more attempts.
+    // Incorrect codes count as failed logins for the account.
+    if claimed == 0 || code != body.code.trim() {
+        lockout::record_failure(&mut **db, &config.lockout, &[account]).await?;
+        return Err(ShieldError::InvalidCode);
+    }
+
+    // The code was correct, so the account's failures can be forgotten.
+    lockout::clear(&mut **db, account).await?;
+
+    // Add the device to the database.
+    let current_time = utils::current_time();
+    query!(
+        "INSERT INTO `devices` (`uid`, `device`, `epoch_lastseen`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `epoch_lastseen` = ?",
+        "INSERT INTO devices (uid, device, epoch_lastseen) VALUES ($1, $2, $3) ON CONFLICT (uid, device) DO UPDATE SET epoch_lastseen = $4",
+        ticket.uid, ticket.device, current_time, current_time
+    ).execute(&mut **db).await?;
+
+    // The code has been used, so it's cleared.
+    // The ticket is kept as redeemed, so a login which requires a code can use it once.
+    query!(
+        "UPDATE `grant_tickets` SET `code` = NULL, `redeemed` = 1 WHERE `ticket` = ?",
+        "UPDATE grant_tickets SET code = NULL, redeemed = 1 WHERE ticket = $1",
+        ticket.ticket
+    ).execute(&mut **db).await?;
+
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, ()))
+}
\ No newline at end of file
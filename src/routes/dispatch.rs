@@ -0,0 +1,99 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use prost::Message;
+use rocket::{http::Status, serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{config::Config, dispatch::{proto, DispatchKeys}};
+
+/// Mounts all routes.
+pub fn mount() -> Vec<Route> {
+    routes![
+        query_region_list,
+        query_cur_region
+    ]
+}
+
+#[derive(Serialize)]
+struct EncryptedRegion {
+    /// The Base64-encoded response, encrypted with the client's public key.
+    content: String,
+
+    /// The Base64-encoded signature of the unencrypted response.
+    sign: String
+}
+
+/// A response type for dispatch responses.
+#[derive(Responder)]
+enum DispatchResponse {
+    /// A Base64-encoded protobuf message.
+    Plain(String),
+
+    /// An encrypted and signed protobuf message.
+    Encrypted(Json<EncryptedRegion>)
+}
+
+/// Sends the list of regions the client can connect to.
+#[get("/query_region_list")]
+fn query_region_list(
+    config: &State<Config>,
+    keys: &State<DispatchKeys>
+) -> String {
+    let region_list = config.dispatch.regions.iter()
+        .map(|region| proto::RegionSimpleInfo {
+            name: region.name.clone(),
+            title: region.title.clone(),
+            r#type: region.r#type.clone(),
+            dispatch_url: region.dispatch_url.clone()
+        })
+        .collect();
+
+    let response = proto::QueryRegionListHttpRsp {
+        retcode: 0,
+        region_list,
+        client_secret_key: keys.secret_key.clone(),
+        client_custom_config_encrypted: keys.encrypt_config(&config.dispatch.region_list_config),
+        enable_login_pc: true
+    };
+    BASE64_STANDARD.encode(response.encode_to_vec())
+}
+
+/// Sends the details of a region, such as its game server's address.
+///
+/// Clients which send a `key_id` get an encrypted and signed response.
+#[get("/query_cur_region/<name>?<key_id>")]
+fn query_cur_region(
+    config: &State<Config>,
+    keys: &State<DispatchKeys>,
+    name: &str,
+    key_id: Option<&str>
+) -> Result<DispatchResponse, Status> {
+    let region = config.dispatch.regions.iter()
+        .find(|region| region.name == name)
+        .ok_or(Status::NotFound)?;
+
+    let response = proto::QueryCurrRegionHttpRsp {
+        retcode: 0,
+        msg: String::new(),
+        region_info: Some(proto::RegionInfo {
+            gateserver_ip: region.gateserver_ip.clone(),
+            gateserver_port: region.gateserver_port,
+            resource_url: region.resource_url.clone(),
+            data_url: region.data_url.clone(),
+            secret_key: keys.secret_key.clone()
+        }),
+        client_secret_key: keys.secret_key.clone(),
+        region_custom_config_encrypted: keys.encrypt_config(&config.dispatch.region_config)
+    };
+    let encoded = response.encode_to_vec();
+
+    // Older clients don't send a key ID, and expect an unencrypted response.
+    let Some(key_id) = key_id else {
+        return Ok(DispatchResponse::Plain(BASE64_STANDARD.encode(encoded)));
+    };
+
+    let content = keys.encrypt(key_id, &encoded).ok_or(Status::BadRequest)?;
+    Ok(DispatchResponse::Encrypted(Json(EncryptedRegion {
+        content: BASE64_STANDARD.encode(content),
+        sign: BASE64_STANDARD.encode(keys.sign(&encoded))
+    })))
+}
@@ -0,0 +1,266 @@
+use log::{error, info};
+use rocket::{fairing::AdHoc, serde::json::Json, Route, State};
+use rocket_db_pools::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{config::Config, constants, db::{query, SDK}, error::{ShieldError, ShieldResult}, guards::{client_type::ClientType, language::Language}, state::AccountState, utils};
+
+/// The example key in `Rocket.toml`, which must be replaced before use.
+const PLACEHOLDER_KEY: &str = "changeme";
+
+/// Mounts all routes, except for `combo_login`.
+///
+/// `combo_login` is mounted by `fairing`, once signing keys are configured.
+pub fn mount() -> Vec<Route> {
+    routes![
+        combo_before_verify,
+        combo_get_config
+    ]
+}
+
+/// Creates a fairing which mounts the combo login route, if every app has a real signing key.
+///
+/// This should be attached after the configuration fairing.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("Combo Routes", |rocket| async {
+        let Some(config) = rocket.state::<Config>() else {
+            error!("Combo logins are disabled, as the configuration isn't loaded");
+            return rocket;
+        };
+
+        let keys = &config.combo.app_keys;
+        if keys.is_empty() {
+            info!("Combo logins are disabled, as `combo.app_keys` is empty");
+            return rocket;
+        }
+        if keys.values().any(|key| key.trim().is_empty() || key == PLACEHOLDER_KEY) {
+            error!("Combo logins are disabled, as `combo.app_keys` has an empty or placeholder key");
+            return rocket;
+        }
+
+        rocket
+            .mount("/hk4e_global", routes![combo_login])
+            .mount("/hk4e_cn", routes![combo_login])
+    })
+}
+
+#[derive(Deserialize)]
+struct ComboLoginRequest {
+    /// The game's app ID.
+    app_id: u32,
+
+    /// The distribution channel of the client.
+    channel_id: u32,
+
+    /// A JSON-encoded `ComboLoginData`.
+    data: String,
+
+    /// The device which is logging in.
+    device: String,
+
+    /// The HMAC-SHA256 signature of the other fields.
+    sign: String
+}
+
+#[derive(Deserialize)]
+struct ComboLoginData {
+    /// The account's unique ID.
+    ///
+    /// The client sends this as a string, but numbers are also accepted.
+    uid: Value,
+
+    /// Whether the account is a guest account.
+    #[serde(default)]
+    guest: bool,
+
+    /// The login token given in `shield_login` or `shield_verify`.
+    ///
+    /// This is unused for guest accounts.
+    #[serde(default)]
+    token: String
+}
+
+#[derive(Serialize)]
+struct ComboLoginResult {
+    /// The unique ID of this combo login.
+    combo_id: String,
+
+    /// The account's unique ID.
+    open_id: String,
+
+    /// The token checked by the game server.
+    combo_token: String,
+
+    /// A JSON-encoded object describing the account.
+    data: String,
+
+    heartbeat: bool,
+    account_type: i32,
+    fatigue_remind: Option<Value>
+}
+
+/// Checks the signature of a combo login request.
+///
+/// The signature covers every field except `sign`, sorted by name.
+fn verify_sign(config: &Config, body: &ComboLoginRequest) -> bool {
+    let Some(key) = config.combo.app_keys.get(&body.app_id.to_string()) else {
+        return false;
+    };
+
+    let message = format!(
+        "app_id={}&channel_id={}&data={}&device={}",
+        body.app_id, body.channel_id, body.data, body.device
+    );
+    utils::hmac_sign(key, &message).eq_ignore_ascii_case(&body.sign)
+}
+
+/// Exchanges a shield login token for a combo token.
+///
+/// The combo token is later checked by the game server.
+#[post("/combo/granter/login/v2/login", data = "<body>")]
+async fn combo_login(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    body: Json<ComboLoginRequest>,
+    client_type: ClientType,
+    language: Language
+) -> ShieldResult {
+    // Check that the request was signed by the client.
+    if !verify_sign(config, &body) {
+        return Err(ShieldError::InvalidSign);
+    }
+
+    // Read the login data.
+    let data: ComboLoginData = serde_json::from_str(&body.data)
+        .map_err(|_| ShieldError::InvalidForm)?;
+    let uid: i32 = match &data.uid {
+        Value::String(uid) => uid.parse().ok(),
+        Value::Number(uid) => uid.as_i64().and_then(|uid| uid.try_into().ok()),
+        _ => None
+    }.ok_or(ShieldError::InvalidForm)?;
+
+    // Get the account.
+    let account = query!(
+        "SELECT `state`, `guest_device` FROM `accounts` WHERE `uid` = ?",
+        "SELECT state, guest_device FROM accounts WHERE uid = $1",
+        uid
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::AccountNotFound)?;
+
+    if data.guest {
+        // Guest accounts are bound to a single device.
+        if account.guest_device.as_deref() != Some(body.device.as_str()) {
+            return Err(ShieldError::InvalidToken);
+        }
+    } else {
+        // Check if the login token exists.
+        let result = query!(
+            "SELECT * FROM `login_tokens` WHERE `uid` = ? AND `token` = ? AND `device` = ?",
+            "SELECT * FROM login_tokens WHERE uid = $1 AND token = $2 AND device = $3",
+            uid, data.token, body.device
+        ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidToken)?;
+
+        // Check if the login token has expired.
+        if result.epoch_expires <= utils::current_time() {
+            return Err(ShieldError::TokenExpired);
+        }
+    }
+
+    // Check that the device has been granted access to the account.
+    query!(
+        "SELECT `uid` FROM `devices` WHERE `uid` = ? AND `device` = ?",
+        "SELECT uid FROM devices WHERE uid = $1 AND device = $2",
+        uid, body.device
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::DeviceChanged)?;
+
+    // Check the account state.
+    if !AccountState::try_from(account.state).is_ok_and(AccountState::can_verify) {
+        return Err(ShieldError::AccountBanned);
+    }
+
+    // Replace any existing combo token for the device.
+    let combo_token = utils::random_token();
+    let current_time = utils::current_time();
+    let expires = current_time + i64::from(config.tokens.lifetime_for(client_type.0));
+
+    // The existing row is updated in place, so the device keeps its combo ID.
+    // On MySQL, `LAST_INSERT_ID(expr)` reports the existing row's ID when it's updated.
+    #[cfg(feature = "mysql")]
+    let combo_id = sqlx::query!(
+        "INSERT INTO `combo_tokens` (`uid`, `device`, `token`, `epoch_created`, `epoch_expires`) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE `combo_id` = LAST_INSERT_ID(`combo_id`), `token` = ?, `epoch_created` = ?, `epoch_expires` = ?",
+        uid, body.device, combo_token, current_time, expires, combo_token, current_time, expires
+    ).execute(&mut **db).await?.last_insert_id();
+
+    #[cfg(not(feature = "mysql"))]
+    let combo_id = sqlx::query_scalar!(
+        r#"INSERT INTO combo_tokens (uid, device, token, epoch_created, epoch_expires) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (uid, device) DO UPDATE SET token = excluded.token, epoch_created = excluded.epoch_created, epoch_expires = excluded.epoch_expires
+        RETURNING combo_id AS "combo_id!: i32""#,
+        uid, body.device, combo_token, current_time, expires
+    ).fetch_one(&mut **db).await?;
+
+    let account_type = if data.guest {
+        constants::ACCOUNT_TYPE_GUEST
+    } else {
+        constants::ACCOUNT_TYPE_NORMAL
+    };
+    let login_data = ComboLoginResult {
+        combo_id: combo_id.to_string(),
+        open_id: uid.to_string(),
+        combo_token,
+        data: serde_json::json!({ "guest": data.guest }).to_string(),
+        heartbeat: false,
+        account_type,
+        fatigue_remind: None
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, login_data))
+}
+
+#[derive(Serialize)]
+struct BeforeVerifyResult {
+    is_heartbeat_required: bool,
+    is_realname_required: bool,
+    is_guardian_required: bool
+}
+
+/// Tells the client which checks are needed before a combo login.
+///
+/// None of these checks are implemented by `pancake`.
+#[post("/combo/granter/login/beforeVerify")]
+fn combo_before_verify(language: Language) -> ShieldResult {
+    let verify_data = BeforeVerifyResult {
+        is_heartbeat_required: false,
+        is_realname_required: false,
+        is_guardian_required: false
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, verify_data))
+}
+
+#[derive(Serialize)]
+struct GetConfigResult {
+    protocol: bool,
+    qr_enabled: bool,
+    log_level: String,
+    announce_url: String,
+    push_alias_type: i32,
+    disable_ysdk_guard: bool,
+    enable_announce_pic_popup: bool
+}
+
+/// Sends the combo configuration, which is loaded before the login screen.
+///
+/// See `ClientConfig` for the values which can be configured.
+#[get("/combo/granter/api/getConfig")]
+fn combo_get_config(config: &State<Config>, language: Language) -> ShieldResult {
+    let settings = &config.client;
+    let config_data = GetConfigResult {
+        protocol: settings.protocol,
+        qr_enabled: settings.qr_enabled,
+        log_level: settings.log_level.clone(),
+        announce_url: settings.announce_url.clone(),
+        push_alias_type: 2,
+        disable_ysdk_guard: true,
+        enable_announce_pic_popup: settings.enable_announce_pic_popup
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, config_data))
+}
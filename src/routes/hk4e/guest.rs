@@ -0,0 +1,181 @@
+use rocket::{serde::json::Json, Route, State};
+use rocket_db_pools::Connection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{config::Config, constants, db::{query, SDK}, error::{ShieldError, ShieldResult}, guards::{client_key::ClientKey, device_id::DeviceId, ip_address::IpAddress, language::Language}, lockout::{self, Subject}, state::AccountState, utils};
+
+/// Mounts all routes.
+pub fn mount() -> Vec<Route> {
+    routes![
+        guest_login,
+        guest_bind
+    ]
+}
+
+#[derive(Serialize)]
+struct GuestLoginResult {
+    /// The guest account's unique ID.
+    ///
+    /// This is the same as the account's `uid`.
+    guest_id: i32,
+
+    /// The type of account which was logged into.
+    account_type: i32
+}
+
+/// Logs in to the guest account bound to the device.
+///
+/// If the device has no guest account, one is created.
+#[post("/mdk/guest/guest/v2/login")]
+async fn guest_login(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    device_id: DeviceId,
+    ip_address: IpAddress,
+    language: Language
+) -> ShieldResult {
+    // Check that guest logins are enabled.
+    if !config.client.guest {
+        return Err(ShieldError::GuestDisabled);
+    }
+
+    // Find the guest account for the device.
+    let account = query!(
+        "SELECT `uid`, `state` FROM `accounts` WHERE `guest_device` = ?",
+        r#"SELECT uid AS "uid!: i32", state FROM accounts WHERE guest_device = $1"#,
+        device_id.0
+    ).fetch_optional(&mut **db).await?;
+
+    let uid = match account {
+        Some(account) => {
+            // Check the account state.
+            if !AccountState::try_from(account.state).is_ok_and(AccountState::can_login) {
+                return Err(ShieldError::AccountBanned);
+            }
+
+            account.uid
+        },
+        None => {
+            // Check if too many guest accounts were created for the client's address.
+            let subjects = [Subject::GuestAddress(&ip_address.0)];
+            if lockout::is_locked(&mut **db, &subjects).await? {
+                return Err(ShieldError::RateLimited);
+            }
+
+            // Count the account towards the address's limit.
+            lockout::record_failure(&mut **db, &config.lockout, &subjects).await?;
+
+            // Create a new guest account.
+            // The device is granted here, so the account's automatic grant is used up.
+            let current_time = utils::current_time();
+
+            #[cfg(feature = "mysql")]
+            let uid = sqlx::query!(
+                "INSERT INTO `accounts` (`guest_device`, `auto_granted`, `epoch_created`) VALUES (?, 1, ?)",
+                device_id.0, current_time
+            ).execute(&mut **db).await?.last_insert_id() as i32;
+
+            #[cfg(not(feature = "mysql"))]
+            let uid = sqlx::query_scalar!(
+                r#"INSERT INTO accounts (guest_device, auto_granted, epoch_created) VALUES ($1, 1, $2) RETURNING uid AS "uid!: i32""#,
+                device_id.0, current_time
+            ).fetch_one(&mut **db).await?;
+
+            uid
+        }
+    };
+
+    // Add the device to the database.
+    let current_time = utils::current_time();
+    query!(
+        "INSERT INTO `devices` (`uid`, `device`, `epoch_lastseen`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `epoch_lastseen` = ?",
+        "INSERT INTO devices (uid, device, epoch_lastseen) VALUES ($1, $2, $3) ON CONFLICT (uid, device) DO UPDATE SET epoch_lastseen = $4",
+        uid, device_id.0, current_time, current_time
+    ).execute(&mut **db).await?;
+
+    let login_data = GuestLoginResult {
+        guest_id: uid,
+        account_type: constants::ACCOUNT_TYPE_GUEST
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, login_data))
+}
+
+#[derive(Deserialize, Validate)]
+struct BindRequest {
+    /// The guest account's unique ID.
+    guest_id: i32,
+
+    /// The username of the full account.
+    #[validate(length(min = 2, max = 64))]
+    account: String,
+
+    /// The email address of the full account.
+    #[validate(email, length(max = 128))]
+    email: String,
+
+    /// The password of the full account.
+    ///
+    /// This is handled the same way as the password in a shield login.
+    password: String,
+
+    /// A flag used to dictate whether the password is encrypted.
+    is_crypto: bool
+}
+
+/// Upgrades a guest account to a full account.
+///
+/// The account keeps its `uid`, and is no longer bound to the device.
+#[post("/mdk/guest/guest/v2/bind", data = "<body>")]
+async fn guest_bind(
+    mut db: Connection<SDK>,
+    body: Json<BindRequest>,
+    device_id: DeviceId,
+    client_key: ClientKey<'_>,
+    language: Language
+) -> ShieldResult {
+    // Check that the guest account belongs to the device.
+    let account = query!(
+        "SELECT `uid`, `state` FROM `accounts` WHERE `uid` = ? AND `guest_device` = ?",
+        "SELECT uid, state FROM accounts WHERE uid = $1 AND guest_device = $2",
+        body.guest_id, device_id.0
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidToken)?;
+
+    if !AccountState::try_from(account.state).is_ok_and(AccountState::can_register) {
+        return Err(ShieldError::AccountBanned);
+    }
+
+    // Validate the user provided data.
+    if body.validate().is_err() {
+        return Err(ShieldError::InvalidForm);
+    }
+
+    // Check if the username or email is already in use.
+    if query!(
+        "SELECT `uid` FROM `accounts` WHERE `name` = ? OR `email` = ?",
+        "SELECT uid FROM accounts WHERE name = $1 OR email = $2",
+        body.account, body.email
+    ).fetch_optional(&mut **db).await?.is_some() {
+        return Err(ShieldError::ExistingUser);
+    }
+
+    // Decode and check the password.
+    let password = utils::decode_password(&client_key, &body.password, body.is_crypto)
+        .ok_or(ShieldError::InvalidForm)?;
+    let password = password.trim();
+    if password.len() < 8 || password.len() > 128 {
+        return Err(ShieldError::InvalidForm);
+    }
+
+    // Hash the password for storage in the database.
+    let hashed = utils::hash_password(password).map_err(|_| ShieldError::System)?;
+
+    // Convert the guest account to a full account.
+    query!(
+        "UPDATE `accounts` SET `name` = ?, `email` = ?, `password` = ?, `guest_device` = NULL WHERE `uid` = ?",
+        "UPDATE accounts SET name = $1, email = $2, password = $3, guest_device = NULL WHERE uid = $4",
+        body.account, body.email, hashed, body.guest_id
+    ).execute(&mut **db).await?;
+
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, ()))
+}
\ No newline at end of file
@@ -1 +1,3 @@
-pub mod shield;
\ No newline at end of file
+pub mod shield;
+pub mod guest;
+pub mod combo;
\ No newline at end of file
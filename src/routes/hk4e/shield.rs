@@ -1,25 +1,34 @@
-use base64::{prelude::BASE64_STANDARD, Engine};
-use rocket::{response::content::RawJson, serde::json::Json, Route};
+use std::collections::HashMap;
+
+use log::warn;
+use rocket::{serde::json::Json, Route, State};
 use rocket_db_pools::Connection;
-use rsa::Pkcs1v15Encrypt;
 use serde::{Deserialize, Serialize};
-use sqlx::{Error, MySqlConnection};
+use sqlx::Connection as _;
 
-use crate::{constants::{self, AccountState, RSA_PRIVATE_KEY}, db::SDK, guards::{device_id::DeviceId, ip_address::IpAddress}, utils};
+use crate::{config::{Config, PasswordlessPolicy}, constants, db::{query, DbConnection, SDK}, error::{ShieldError, ShieldResult}, guards::{client_key::ClientKey, client_type::ClientType, device_id::DeviceId, ip_address::IpAddress, language::Language}, lockout::{self, Subject}, sms::SmsSender, state::{self, AccountState, TransitionError}, utils};
 
 /// Mounts all routes.
 pub fn mount() -> Vec<Route> {
     routes![
         shield_login,
-        shield_verify
+        shield_verify,
+        shield_reactivate,
+        shield_send_captcha,
+        shield_login_mobile,
+        shield_load_config
     ]
 }
 
 /// Checks if the given device needs to be authenticated.
-async fn needs_grant(db: &mut MySqlConnection, uid: i32, device_id: &String) -> bool {
+///
+/// Only the first device to log in to an account is granted automatically.
+/// Every device after it needs a grant, even once the account's devices are forgotten or revoked.
+async fn needs_grant(db: &mut DbConnection, uid: i32, device_id: &String) -> bool {
     // Check the database for an existing device entry.
-    let Ok(result) = sqlx::query!(
+    let Ok(result) = query!(
         "SELECT * FROM `devices` WHERE `uid` = ? AND `device` = ?",
+        "SELECT * FROM devices WHERE uid = $1 AND device = $2",
         uid, device_id
     ).fetch_optional(&mut *db).await else {
         return true;
@@ -31,14 +40,27 @@ async fn needs_grant(db: &mut MySqlConnection, uid: i32, device_id: &String) ->
     }
 
     // Check if this is the first device grant.
-    let Ok(result) = sqlx::query!(
+    let Ok(result) = query!(
         "SELECT * FROM `devices` WHERE `uid` = ?",
+        "SELECT * FROM devices WHERE uid = $1",
         uid
     ).fetch_all(&mut *db).await else {
         return true;
     };
+    if !result.is_empty() {
+        return true;
+    }
 
-    !result.is_empty()
+    // Use up the account's automatic grant, so only one device can have it.
+    let Ok(result) = query!(
+        "UPDATE `accounts` SET `auto_granted` = 1 WHERE `uid` = ? AND `auto_granted` = 0",
+        "UPDATE accounts SET auto_granted = 1 WHERE uid = $1 AND auto_granted = 0",
+        uid
+    ).execute(&mut *db).await else {
+        return true;
+    };
+
+    result.rows_affected() == 0
 }
 
 #[derive(Serialize)]
@@ -133,75 +155,120 @@ struct LoginRequest {
     pub is_crypto: bool
 }
 
-/// A response type for shield-related responses.
-#[derive(Responder)]
-enum ShieldResponse {
-    /// This is returned when an error occurs.
-    #[response(status = 200)]
-    CodedError(RawJson<String>),
+/// Options which change how `do_login` issues tokens and grants.
+struct LoginOptions {
+    /// How long, in seconds, a new login token is valid for.
+    token_lifetime: u32,
 
-    /// This is returned in all instances of where a `retcode` is provided.
-    #[response(status = 200)]
-    CodedResponse(RawJson<String>)
+    /// Whether a new login token should always be issued.
+    ///
+    /// Otherwise, an unexpired token for the device is reused.
+    rotate_token: bool,
+
+    /// Whether every login needs a grant code, even from a known device.
+    ///
+    /// A redeemed code lets a single login through.
+    require_code: bool
+}
+
+impl LoginOptions {
+    /// Creates the default options for the given client type.
+    fn new(config: &Config, client_type: ClientType) -> Self {
+        LoginOptions {
+            token_lifetime: config.tokens.lifetime_for(client_type.0),
+            rotate_token: false,
+            require_code: false
+        }
+    }
 }
 
 /// Performs database queries to complete a login request.
 async fn do_login(
-    db: &mut MySqlConnection,
+    db: &mut DbConnection,
     device_id: String,
     ip_address: String,
     account: AccountData,
-    account_state: i32
-) -> ShieldResponse {
-    // Check if the account needs to be reactivated.
-    let reactivate_ticket = match account_state.try_into().unwrap() {
-        AccountState::PendingDelete => {
-            // Generate a reactivation ticket.
-            let ticket = utils::random_token();
-            // Insert the ticket into the database.
-            sqlx::query!(
-                "INSERT INTO `reactivate_tickets` (`ticket`, `uid`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `ticket` = ?",
-                ticket, account.uid, ticket
-            ).execute(&mut *db).await.ok();
-
-            Some(ticket)
-        },
-        _ => None
+    account_state: AccountState,
+    options: LoginOptions,
+    language: Language
+) -> ShieldResult {
+    // Check if the device needs a grant.
+    let grant_required = if options.require_code {
+        // Use up a grant code redeemed for this device, if there is one.
+        let redeemed = query!(
+            "DELETE FROM `grant_tickets` WHERE `uid` = ? AND `device` = ? AND `redeemed` = 1 AND `epoch_created` > ?",
+            "DELETE FROM grant_tickets WHERE uid = $1 AND device = $2 AND redeemed = 1 AND epoch_created > $3",
+            account.uid, device_id, utils::current_time() - constants::TICKET_LIFETIME
+        ).execute(&mut *db).await?.rows_affected();
+
+        redeemed == 0
+    } else {
+        needs_grant(&mut *db, account.uid, &device_id).await
     };
 
-    // Check if the device needs a grant.
     let grant_ticket = {
-        if needs_grant(&mut *db, account.uid, &device_id).await {
+        if grant_required {
             // Generate a grant ticket.
             let ticket = utils::random_token();
+            let current_time = utils::current_time();
+            let expired_before = current_time - constants::TICKET_LIFETIME;
             // Insert the ticket into the database.
-            sqlx::query!(
-                "INSERT INTO `grant_tickets` (`ticket`, `uid`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `ticket` = ?",
-                ticket, account.uid, ticket
-            ).execute(&mut *db).await.ok();
+            // An unexpired ticket keeps its attempts, send time and age, so logging in again doesn't reset them.
+            query!(
+                "INSERT INTO `grant_tickets` (`ticket`, `uid`, `device`, `epoch_created`) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE `ticket` = ?, `code` = NULL, `redeemed` = 0, `attempts` = CASE WHEN `epoch_created` > ? THEN `attempts` ELSE 0 END, `epoch_sent` = CASE WHEN `epoch_created` > ? THEN `epoch_sent` ELSE NULL END, `epoch_created` = CASE WHEN `epoch_created` > ? THEN `epoch_created` ELSE ? END",
+                "INSERT INTO grant_tickets (ticket, uid, device, epoch_created) VALUES ($1, $2, $3, $4) ON CONFLICT (uid, device) DO UPDATE SET ticket = $5, code = NULL, redeemed = 0, attempts = CASE WHEN grant_tickets.epoch_created > $6 THEN grant_tickets.attempts ELSE 0 END, epoch_sent = CASE WHEN grant_tickets.epoch_created > $7 THEN grant_tickets.epoch_sent ELSE NULL END, epoch_created = CASE WHEN grant_tickets.epoch_created > $8 THEN grant_tickets.epoch_created ELSE $9 END",
+                ticket, account.uid, device_id, current_time, ticket, expired_before, expired_before, expired_before, current_time
+            ).execute(&mut *db).await?;
 
             Some(ticket)
         } else {
             // Add the device to the database.
             let current_time = utils::current_time();
-            sqlx::query!(
+            query!(
                 "INSERT INTO `devices` (`uid`, `device`, `epoch_lastseen`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `epoch_lastseen` = ?",
+                "INSERT INTO devices (uid, device, epoch_lastseen) VALUES ($1, $2, $3) ON CONFLICT (uid, device) DO UPDATE SET epoch_lastseen = $4",
                 account.uid, device_id, current_time, current_time
-            ).execute(&mut *db).await.ok();
+            ).execute(&mut *db).await?;
 
             None
         }
     };
 
+    // Check if the account needs to be reactivated.
+    // The ticket is only issued once the device has passed its grant, and only to that device.
+    let reactivate_ticket = match account_state {
+        AccountState::PendingDelete if grant_ticket.is_none() => {
+            // Generate a reactivation ticket.
+            let ticket = utils::random_token();
+            let current_time = utils::current_time();
+            // Insert the ticket into the database.
+            query!(
+                "INSERT INTO `reactivate_tickets` (`ticket`, `uid`, `device`, `epoch_created`) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE `ticket` = ?, `device` = ?, `epoch_created` = ?",
+                "INSERT INTO reactivate_tickets (ticket, uid, device, epoch_created) VALUES ($1, $2, $3, $4) ON CONFLICT (uid) DO UPDATE SET ticket = $5, device = $6, epoch_created = $7",
+                ticket, account.uid, device_id, current_time, ticket, device_id, current_time
+            ).execute(&mut *db).await?;
+
+            Some(ticket)
+        },
+        _ => None
+    };
+
     // Generate the login token.
-    let token = {
-        // Check if an existing token is present.
-        let result = match sqlx::query!(
-            "SELECT * FROM `login_tokens` WHERE `uid` = ? AND `device` = ?",
-            account.uid, device_id
-        ).fetch_optional(&mut *db).await {
-            Ok(Some(entry)) => Some(entry),
-            _ => None
+    // No token is issued until a pending grant or reactivation has been completed.
+    let token = if grant_ticket.is_some() || reactivate_ticket.is_some() {
+        String::new()
+    } else {
+        let current_time = utils::current_time();
+
+        // Check if an existing, unexpired token is present.
+        let result = if options.rotate_token {
+            None
+        } else {
+            query!(
+                "SELECT * FROM `login_tokens` WHERE `uid` = ? AND `device` = ? AND `epoch_expires` > ?",
+                "SELECT * FROM login_tokens WHERE uid = $1 AND device = $2 AND epoch_expires > $3",
+                account.uid, device_id, current_time
+            ).fetch_optional(&mut *db).await?
         };
 
         match result {
@@ -209,11 +276,13 @@ async fn do_login(
             None => {
                 // Generate a new token.
                 let token = utils::random_token();
+                let expires = current_time + i64::from(options.token_lifetime);
                 // Insert the token into the database.
-                sqlx::query!(
-                    "INSERT INTO `login_tokens` (`uid`, `device`, `token`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `token` = ?",
-                    account.uid, device_id, token, token
-                ).execute(&mut *db).await.ok();
+                query!(
+                    "INSERT INTO `login_tokens` (`uid`, `device`, `token`, `epoch_created`, `epoch_expires`) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE `token` = ?, `epoch_created` = ?, `epoch_expires` = ?",
+                    "INSERT INTO login_tokens (uid, device, token, epoch_created, epoch_expires) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (uid, device) DO UPDATE SET token = $6, epoch_created = $7, epoch_expires = $8",
+                    account.uid, device_id, token, current_time, expires, token, current_time, expires
+                ).execute(&mut *db).await?;
 
                 token
             }
@@ -243,54 +312,74 @@ async fn do_login(
         realname_operation: constants::REALNAME_OP_NONE.to_string()
     };
 
-    ShieldResponse::CodedResponse(utils::message_response(constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, login_data))
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, login_data))
 }
 
 /// Handles a full login request from the user.
 #[post("/mdk/shield/api/login", data = "<body>")]
+#[allow(clippy::too_many_arguments)]
 async fn shield_login(
     mut db: Connection<SDK>,
+    config: &State<Config>,
     body: Json<LoginRequest>, 
     device_id: DeviceId,
-    ip_address: IpAddress
-) -> ShieldResponse {
+    ip_address: IpAddress,
+    client_type: ClientType,
+    client_key: ClientKey<'_>,
+    language: Language
+) -> ShieldResult {
+    // Check if the client's address is locked out.
+    let address = Subject::Address(&ip_address.0);
+    if lockout::is_locked(&mut **db, &[address]).await? {
+        return Err(ShieldError::LockedOut);
+    }
+
     // Fetch the account data from the database.
-    let Ok(account) = sqlx::query!(
+    let Some(account) = query!(
         "SELECT * FROM `accounts` WHERE `name` = ? OR `email` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, password, state, email_verified FROM accounts WHERE name = $1 OR email = $2"#,
         body.account, body.account
-    ).fetch_one(&mut **db).await else {
-        return ShieldResponse::CodedError(utils::system_error());
+    ).fetch_optional(&mut **db).await? else {
+        lockout::record_failure(&mut **db, &config.lockout, &[address]).await?;
+        return Err(ShieldError::AccountNotFound);
     };
 
-    // Check the account's satte.
-    if account.state != AccountState::Active || account.state != AccountState::PendingDelete {
-        return ShieldResponse::CodedError(
-            utils::message_response(constants::RESPONSE_LOGIN_FAILED, constants::MESSAGE_INVALID_CREDS, ())
-        );
+    // Check if the account is locked out.
+    let subjects = [Subject::Account(account.uid), address];
+    if lockout::is_locked(&mut **db, &subjects).await? {
+        return Err(ShieldError::LockedOut);
     }
 
-    // Verify the password of the account.
-    let Ok(password) = BASE64_STANDARD.decode(&body.password) else {
-        return ShieldResponse::CodedError(utils::system_error());
+    // Check the account's state.
+    let state = match AccountState::try_from(account.state) {
+        Ok(state) if state.can_login() => state,
+        _ => return Err(ShieldError::AccountBanned)
     };
-    let password = if body.is_crypto {
-        match RSA_PRIVATE_KEY.decrypt(
-            Pkcs1v15Encrypt, &password
-        ) {
-            Ok(password) => password,
-            _ => return ShieldResponse::CodedError(utils::system_error())
-        }
-    } else {
-        password
+
+    // Verify the password of the account.
+    let password = utils::decode_password(&client_key, &body.password, body.is_crypto)
+        .ok_or(ShieldError::InvalidForm)?;
+
+    let mut options = LoginOptions {
+        rotate_token: true,
+        ..LoginOptions::new(config, client_type)
     };
-    let password = String::from_utf8(password).unwrap_or_default();
-    
-    if let Some(hashed_password) = account.password {
-        // This will only verify the password if one is set.
-        if !utils::verify_password(&password, &hashed_password) {
-            return ShieldResponse::CodedError(
-                utils::message_response(constants::RESPONSE_LOGIN_FAILED, constants::MESSAGE_INVALID_CREDS, ())
-            );
+    match account.password {
+        Some(hashed_password) => {
+            if !utils::verify_password(&password, &hashed_password) {
+                lockout::record_failure(&mut **db, &config.lockout, &subjects).await?;
+                return Err(ShieldError::WrongPassword);
+            }
+
+            // The password was correct, so the account's failures can be forgotten.
+            lockout::clear(&mut **db, Subject::Account(account.uid)).await?;
+        },
+        // Accounts without a password are handled by the configured policy.
+        // Their failures are only forgotten once a grant code is redeemed.
+        None => match config.accounts.passwordless {
+            PasswordlessPolicy::Dev if cfg!(debug_assertions) => (),
+            PasswordlessPolicy::Code => options.require_code = true,
+            _ => return Err(ShieldError::WrongPassword)
         }
     }
 
@@ -300,10 +389,10 @@ async fn shield_login(
         name: utils::mask_string(&account.name.unwrap_or_default()),
         email: utils::mask_string(&account.email.unwrap_or_default()),
         mobile: utils::mask_string(&account.mobile.unwrap_or_default()),
-        is_email_verify: false,
+        is_email_verify: account.email_verified != 0,
         ..Default::default()
     };
-    do_login(&mut **db, device_id.0, ip_address.0, account_data, account.state).await
+    do_login(&mut **db, device_id.0, ip_address.0, account_data, state, options, language).await
 }
 
 #[derive(Deserialize)]
@@ -319,43 +408,97 @@ struct VerifyRequest {
 #[post("/mdk/shield/api/verify", data = "<body>")]
 async fn shield_verify(
     mut db: Connection<SDK>,
+    config: &State<Config>,
     body: Json<VerifyRequest>,
     device_id: DeviceId,
-    ip_address: IpAddress
-) -> ShieldResponse {
+    ip_address: IpAddress,
+    client_type: ClientType,
+    language: Language
+) -> ShieldResult {
     // Check if the login token exists.
-    let result = match sqlx::query!(
+    let result = query!(
         "SELECT * FROM `login_tokens` WHERE `uid` = ? AND `token` = ? AND `device` = ?",
-        body.uid, body.token, device_id.0
-    ).fetch_one(&mut **db).await {
-        Ok(result) => result,
-        Err(Error::RowNotFound) => return ShieldResponse::CodedResponse(
-            utils::message_response(constants::RESPONSE_LOGIN_FAILED, constants::MESSAGE_BAD_TOKEN, ())
-        ),
-        Err(_) => return ShieldResponse::CodedResponse(utils::system_error())
-    };
+        "SELECT * FROM login_tokens WHERE uid = $1 AND token = $2 AND device = $3",
+        body.uid as i32, body.token, device_id.0
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidToken)?;
+
+    // Check if the login token has expired.
+    if result.epoch_expires <= utils::current_time() {
+        return Err(ShieldError::TokenExpired);
+    }
 
     // Get the account associated with the token.
-    let account = match sqlx::query!(
+    let account = query!(
         "SELECT * FROM `accounts` WHERE `uid` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, password, state, email_verified FROM accounts WHERE uid = $1"#,
         result.uid
-    ).fetch_one(&mut **db).await {
-        Ok(account) => account,
-        _ => return ShieldResponse::CodedResponse(utils::system_error())
-    };
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::AccountNotFound)?;
 
     // Check the account state.
-    if account.state != AccountState::Active {
-        return ShieldResponse::CodedResponse(
-            utils::message_response(constants::RESPONSE_LOGIN_FAILED, constants::MESSAGE_BAD_TOKEN, ())
-        );
-    }
+    // Accounts pending deletion have to log in again, which offers them reactivation.
+    let state = match AccountState::try_from(account.state) {
+        Ok(state) if state.can_verify() => state,
+        Ok(AccountState::PendingDelete) => return Err(ShieldError::InvalidToken),
+        _ => return Err(ShieldError::AccountBanned)
+    };
 
     // Compare the device ID to the stored one.
     if result.device != device_id.0 {
-        return ShieldResponse::CodedResponse(
-            utils::message_response(constants::RESPONSE_LOGIN_FAILED, constants::MESSAGE_NEW_DEVICE, ())
-        );
+        return Err(ShieldError::DeviceChanged);
+    }
+
+    // Prepare the account data.
+    let account_data = AccountData {
+        uid: account.uid,
+        name: utils::mask_string(&account.name.unwrap_or_default()),
+        email: utils::mask_string(&account.email.unwrap_or_default()),
+        mobile: utils::mask_string(&account.mobile.unwrap_or_default()),
+        is_email_verify: account.email_verified != 0,
+        ..Default::default()
+    };
+    let options = LoginOptions::new(config, client_type);
+    do_login(&mut **db, device_id.0, ip_address.0, account_data, state, options, language).await
+}
+
+#[derive(Deserialize)]
+struct ReactivateRequest {
+    /// The reactivation ticket given in the login response.
+    ticket: String
+}
+
+/// Reactivates an account which is pending deletion, given a reactivation ticket.
+#[post("/mdk/shield/api/reactivateAccount", data = "<body>")]
+async fn shield_reactivate(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    body: Json<ReactivateRequest>,
+    device_id: DeviceId,
+    ip_address: IpAddress,
+    client_type: ClientType,
+    language: Language
+) -> ShieldResult {
+    // Find the unexpired reactivation ticket issued to the device.
+    let ticket = query!(
+        "SELECT * FROM `reactivate_tickets` WHERE `ticket` = ? AND `device` = ? AND `epoch_created` > ?",
+        "SELECT * FROM reactivate_tickets WHERE ticket = $1 AND device = $2 AND epoch_created > $3",
+        body.ticket, device_id.0, utils::current_time() - constants::TICKET_LIFETIME
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidTicket)?;
+
+    // Get the account associated with the ticket.
+    let account = query!(
+        "SELECT * FROM `accounts` WHERE `uid` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, password, state, email_verified FROM accounts WHERE uid = $1"#,
+        ticket.uid
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::AccountNotFound)?;
+
+    // Only accounts which are pending deletion can be reactivated.
+    if account.state != AccountState::PendingDelete {
+        return Err(ShieldError::InvalidTicket);
+    }
+
+    // Move the account back to the active state.
+    if !reactivate_account(&mut **db, account.uid, &ticket.ticket).await? {
+        return Err(ShieldError::InvalidTicket);
     }
 
     // Prepare the account data.
@@ -364,8 +507,282 @@ async fn shield_verify(
         name: utils::mask_string(&account.name.unwrap_or_default()),
         email: utils::mask_string(&account.email.unwrap_or_default()),
         mobile: utils::mask_string(&account.mobile.unwrap_or_default()),
-        is_email_verify: false,
+        is_email_verify: account.email_verified != 0,
         ..Default::default()
     };
-    do_login(&mut **db, device_id.0, ip_address.0, account_data, account.state).await
-}
\ No newline at end of file
+    // The login which issued the ticket already passed the device's grant, including any code it required.
+    let options = LoginOptions::new(config, client_type);
+    do_login(&mut **db, device_id.0, ip_address.0, account_data, AccountState::Active, options, language).await
+}
+
+/// Reactivates an account in a single transaction.
+///
+/// This uses up the reactivation ticket, and cancels the account's scheduled deletion.
+/// Returns `false` if the ticket was already used.
+async fn reactivate_account(db: &mut DbConnection, uid: i32, ticket: &str) -> Result<bool, TransitionError> {
+    let mut tx = db.begin().await?;
+
+    // Deleting the ticket first means only one request can use it.
+    let result = query!(
+        "DELETE FROM `reactivate_tickets` WHERE `ticket` = ?",
+        "DELETE FROM reactivate_tickets WHERE ticket = $1",
+        ticket
+    ).execute(&mut *tx).await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    state::transition(&mut tx, uid, AccountState::Active, "reactivated by ticket").await?;
+
+    // The account is no longer scheduled for deletion.
+    query!(
+        "UPDATE `accounts` SET `epoch_delete` = NULL WHERE `uid` = ?",
+        "UPDATE accounts SET epoch_delete = NULL WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[derive(Deserialize)]
+struct SendCaptchaRequest {
+    /// The mobile number to send the code to.
+    mobile: String
+}
+
+/// Sends a login code to a mobile number.
+#[post("/mdk/shield/api/loginCaptcha", data = "<body>")]
+async fn shield_send_captcha(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    sender: &State<Box<dyn SmsSender>>,
+    body: Json<SendCaptchaRequest>,
+    ip_address: IpAddress,
+    language: Language
+) -> ShieldResult {
+    // Check that the mobile number is valid.
+    let mobile = body.mobile.trim();
+    if !utils::is_valid_mobile(mobile) {
+        return Err(ShieldError::InvalidMobile);
+    }
+
+    // Check if too many codes were sent to the number, or for the client's address.
+    let subjects = [Subject::Mobile(mobile), Subject::SmsAddress(&ip_address.0)];
+    if lockout::is_locked(&mut **db, &subjects).await? {
+        return Err(ShieldError::RateLimited);
+    }
+
+    // Check if a code was sent recently.
+    let current_time = utils::current_time();
+    let entry = query!(
+        "SELECT `epoch_sent` FROM `mobile_captchas` WHERE `mobile` = ?",
+        "SELECT epoch_sent FROM mobile_captchas WHERE mobile = $1",
+        mobile
+    ).fetch_optional(&mut **db).await?;
+    if entry.is_some_and(|entry| entry.epoch_sent + constants::SMS_RESEND_INTERVAL > current_time) {
+        return Err(ShieldError::RateLimited);
+    }
+
+    // Count the code towards the number's and the address's limits.
+    lockout::record_failure(&mut **db, &config.lockout, &subjects).await?;
+
+    // Generate a new code.
+    // Incorrect guesses are only forgotten once the previous code has expired.
+    let code = utils::random_code();
+    let expired_before = current_time - constants::SMS_CODE_LIFETIME;
+    query!(
+        "INSERT INTO `mobile_captchas` (`mobile`, `code`, `attempts`, `epoch_sent`) VALUES (?, ?, 0, ?) ON DUPLICATE KEY UPDATE `code` = ?, `attempts` = CASE WHEN `epoch_sent` > ? THEN `attempts` ELSE 0 END, `epoch_sent` = ?",
+        "INSERT INTO mobile_captchas (mobile, code, attempts, epoch_sent) VALUES ($1, $2, 0, $3) ON CONFLICT (mobile) DO UPDATE SET code = $4, attempts = CASE WHEN mobile_captchas.epoch_sent > $5 THEN mobile_captchas.attempts ELSE 0 END, epoch_sent = $6",
+        mobile, code, current_time, code, expired_before, current_time
+    ).execute(&mut **db).await?;
+
+    // Send the code to the mobile number.
+    let message = format!(
+        "Your login verification code is {}. It expires in {} minutes.",
+        code, constants::SMS_CODE_LIFETIME / 60
+    );
+    if let Err(error) = sender.send(mobile, &message).await {
+        warn!("Failed to send a login code to {}: {}", mobile, error);
+        return Err(ShieldError::System);
+    }
+
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, ()))
+}
+
+#[derive(Deserialize)]
+struct MobileLoginRequest {
+    /// The mobile number of the account.
+    mobile: String,
+
+    /// The code sent in `shield_send_captcha`.
+    captcha: String
+}
+
+/// Handles a login request using a mobile number and a login code.
+///
+/// If no account has the mobile number, one is created.
+#[post("/mdk/shield/api/loginMobile", data = "<body>")]
+async fn shield_login_mobile(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    body: Json<MobileLoginRequest>,
+    device_id: DeviceId,
+    ip_address: IpAddress,
+    client_type: ClientType,
+    language: Language
+) -> ShieldResult {
+    let mobile = body.mobile.trim();
+
+    // Find the code sent to the mobile number.
+    let entry = query!(
+        "SELECT * FROM `mobile_captchas` WHERE `mobile` = ?",
+        "SELECT * FROM mobile_captchas WHERE mobile = $1",
+        mobile
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidCode)?;
+
+    // Use up an attempt before comparing, so concurrent guesses can't exceed the limit.
+    // This fails once the code has expired or run out of attempts.
+    let claimed = query!(
+        "UPDATE `mobile_captchas` SET `attempts` = `attempts` + 1 WHERE `mobile` = ? AND `attempts` < ? AND `epoch_sent` > ?",
+        "UPDATE mobile_captchas SET attempts = attempts + 1 WHERE mobile = $1 AND attempts < $2 AND epoch_sent > $3",
+        mobile, constants::SMS_MAX_ATTEMPTS as i32, utils::current_time() - constants::SMS_CODE_LIFETIME
+    ).execute(&mut **db).await?.rows_affected();
+    if claimed == 0 {
+        return Err(ShieldError::ExpiredCode);
+    }
+
+    // Compare the code to the one which was sent.
+    if entry.code != body.captcha.trim() {
+        return Err(ShieldError::InvalidCode);
+    }
+
+    // The code has been used, so it can be removed.
+    // Only one request can remove it, and only if it wasn't replaced in the meantime.
+    let removed = query!(
+        "DELETE FROM `mobile_captchas` WHERE `mobile` = ? AND `code` = ?",
+        "DELETE FROM mobile_captchas WHERE mobile = $1 AND code = $2",
+        mobile, entry.code
+    ).execute(&mut **db).await?.rows_affected();
+    if removed == 0 {
+        return Err(ShieldError::InvalidCode);
+    }
+
+    // Fetch the account data from the database.
+    let account = query!(
+        "SELECT * FROM `accounts` WHERE `mobile` = ?",
+        r#"SELECT uid AS "uid!: i32", name, email, mobile, password, state, email_verified FROM accounts WHERE mobile = $1"#,
+        mobile
+    ).fetch_optional(&mut **db).await?;
+
+    let (account_data, state) = match account {
+        Some(account) => {
+            // Check the account's state.
+            let state = match AccountState::try_from(account.state) {
+                Ok(state) if state.can_login() => state,
+                _ => return Err(ShieldError::AccountBanned)
+            };
+
+            let account_data = AccountData {
+                uid: account.uid,
+                name: utils::mask_string(&account.name.unwrap_or_default()),
+                email: utils::mask_string(&account.email.unwrap_or_default()),
+                mobile: utils::mask_string(&account.mobile.unwrap_or_default()),
+                is_email_verify: account.email_verified != 0,
+                ..Default::default()
+            };
+            (account_data, state)
+        },
+        None => {
+            // Create a new account for the mobile number.
+            let current_time = utils::current_time();
+
+            #[cfg(feature = "mysql")]
+            let uid = sqlx::query!(
+                "INSERT INTO `accounts` (`mobile`, `epoch_created`) VALUES (?, ?)",
+                mobile, current_time
+            ).execute(&mut **db).await?.last_insert_id() as i32;
+
+            #[cfg(not(feature = "mysql"))]
+            let uid = sqlx::query_scalar!(
+                r#"INSERT INTO accounts (mobile, epoch_created) VALUES ($1, $2) RETURNING uid AS "uid!: i32""#,
+                mobile, current_time
+            ).fetch_one(&mut **db).await?;
+
+            let account_data = AccountData {
+                uid,
+                mobile: utils::mask_string(mobile),
+                ..Default::default()
+            };
+            (account_data, AccountState::Active)
+        }
+    };
+
+    let options = LoginOptions {
+        rotate_token: true,
+        ..LoginOptions::new(config, client_type)
+    };
+    do_login(&mut **db, device_id.0, ip_address.0, account_data, state, options, language).await
+}
+
+#[derive(Serialize)]
+struct LoadConfigResult {
+    /// The game which the configuration applies to, such as `hk4e_global`.
+    game_key: String,
+
+    /// The name of the client platform.
+    client: &'static str,
+
+    identity: &'static str,
+    scene: &'static str,
+    guest: bool,
+    server_guest: bool,
+    disable_regist: bool,
+    register_url: String,
+    enable_email_captcha: bool,
+    disable_mmt: bool,
+    thirdparty: Vec<String>,
+    thirdparty_ignore: HashMap<String, String>,
+    enable_ps_bind_account: bool,
+    initialize_firebase: bool
+}
+
+/// Sends the SDK configuration, which decides what the login screen shows.
+///
+/// See `ClientConfig` for the values which can be configured.
+#[get("/mdk/shield/api/loadConfig?<client>&<game_key>")]
+fn shield_load_config(
+    config: &State<Config>,
+    client: Option<u8>,
+    game_key: Option<&str>,
+    client_type: ClientType,
+    language: Language
+) -> ShieldResult {
+    // The client type is sent in the query, but the header is used as a fallback.
+    let client_name = match client.unwrap_or(client_type.0) {
+        1 => "IOS",
+        2 => "Android",
+        _ => "PC"
+    };
+    let settings = &config.client;
+
+    let config_data = LoadConfigResult {
+        game_key: game_key.unwrap_or("hk4e_global").to_string(),
+        client: client_name,
+        identity: "I_IDENTITY",
+        scene: "S_NORMAL",
+        guest: settings.guest,
+        server_guest: settings.guest,
+        disable_regist: settings.disable_regist,
+        register_url: settings.register_url.clone(),
+        enable_email_captcha: settings.enable_email_captcha,
+        disable_mmt: settings.disable_mmt,
+        thirdparty: settings.thirdparty.clone(),
+        thirdparty_ignore: settings.thirdparty.iter()
+            .map(|method| (method.clone(), String::new()))
+            .collect(),
+        enable_ps_bind_account: false,
+        initialize_firebase: false
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, config_data))
+}
@@ -0,0 +1,68 @@
+use rocket::{serde::json::Json, Route, State};
+use rocket_db_pools::Connection;
+
+use pancake_client::{ComboVerifyRequest, VerifiedAccount};
+
+use crate::{config::Config, constants, db::{query, SDK}, error::{ShieldError, ShieldResult}, guards::language::Language, utils};
+
+/// Mounts all routes.
+pub fn mount() -> Vec<Route> {
+    routes![
+        combo_verify
+    ]
+}
+
+/// Checks the signature and timestamp of a server-to-server request.
+fn verify_sign(config: &Config, body: &ComboVerifyRequest) -> bool {
+    let Some(secret) = &config.internal.secret else {
+        return false;
+    };
+
+    if utils::current_time().abs_diff(body.timestamp) > u64::from(config.internal.max_skew) {
+        return false;
+    }
+
+    utils::hmac_sign(secret, &body.sign_message()).eq_ignore_ascii_case(&body.sign)
+}
+
+/// Verifies a combo token for a game server.
+///
+/// See `pancake_client::SdkClient` for a client which calls this.
+#[post("/combo/verify", data = "<body>")]
+async fn combo_verify(
+    mut db: Connection<SDK>,
+    config: &State<Config>,
+    body: Json<ComboVerifyRequest>,
+    language: Language
+) -> ShieldResult {
+    // Check that the request came from a game server.
+    if !verify_sign(config, &body) {
+        return Err(ShieldError::InvalidSign);
+    }
+
+    // Check if the combo token exists.
+    let result = query!(
+        "SELECT * FROM `combo_tokens` WHERE `uid` = ? AND `token` = ?",
+        r#"SELECT combo_id AS "combo_id!: i32", uid, device, token, epoch_created, epoch_expires FROM combo_tokens WHERE uid = $1 AND token = $2"#,
+        body.uid, body.combo_token
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::InvalidToken)?;
+
+    // Check if the combo token has expired.
+    if result.epoch_expires <= utils::current_time() {
+        return Err(ShieldError::TokenExpired);
+    }
+
+    // Get the account's current state.
+    let account = query!(
+        "SELECT `state` FROM `accounts` WHERE `uid` = ?",
+        "SELECT state FROM accounts WHERE uid = $1",
+        result.uid
+    ).fetch_optional(&mut **db).await?.ok_or(ShieldError::AccountNotFound)?;
+
+    let verified = VerifiedAccount {
+        uid: result.uid,
+        state: account.state,
+        device: result.device
+    };
+    Ok(utils::message_response(language, constants::RESPONSE_SUCCESS, constants::MESSAGE_SUCCESS, verified))
+}
@@ -1,2 +1,6 @@
 pub mod hk4e;
-pub mod account;
\ No newline at end of file
+pub mod account;
+pub mod device;
+pub mod internal;
+pub mod dispatch;
+pub mod admin;
\ No newline at end of file
@@ -0,0 +1,69 @@
+use log::info;
+use rocket::fairing::AdHoc;
+use rocket::tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::config::{Config, SmsConfig, SmsSenderKind};
+
+/// Delivers text messages, such as login codes, to mobile numbers.
+///
+/// The sender used by the server is stored in Rocket's managed state.
+#[rocket::async_trait]
+pub trait SmsSender: Send + Sync {
+    /// Sends the message to the mobile number.
+    async fn send(&self, mobile: &str, message: &str) -> anyhow::Result<()>;
+}
+
+/// A sender which writes every message to the log.
+///
+/// This is the default sender, and allows testing without an SMS gateway.
+pub struct LogSmsSender;
+
+#[rocket::async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, mobile: &str, message: &str) -> anyhow::Result<()> {
+        info!("SMS for {}: {}", mobile, message);
+        Ok(())
+    }
+}
+
+/// A sender which appends every message to a file.
+pub struct FileSmsSender {
+    /// The path of the file to write to.
+    pub path: String
+}
+
+#[rocket::async_trait]
+impl SmsSender for FileSmsSender {
+    async fn send(&self, mobile: &str, message: &str) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        file.write_all(format!("{}: {}\n", mobile, message).as_bytes()).await?;
+        Ok(())
+    }
+}
+
+/// Creates the SMS sender described by the configuration.
+pub fn sender_for(config: &SmsConfig) -> Box<dyn SmsSender> {
+    match config.sender {
+        SmsSenderKind::Log => Box::new(LogSmsSender),
+        SmsSenderKind::File => Box::new(FileSmsSender { path: config.path.clone() })
+    }
+}
+
+/// Creates a fairing which adds the configured SMS sender to Rocket's managed state.
+///
+/// This should be attached after the configuration fairing.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("SMS Sender", |rocket| async {
+        let sender: Box<dyn SmsSender> = match rocket.state::<Config>() {
+            Some(config) => sender_for(&config.sms),
+            None => Box::new(LogSmsSender)
+        };
+
+        rocket.manage(sender)
+    })
+}
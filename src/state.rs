@@ -0,0 +1,156 @@
+use sqlx::Connection;
+
+use crate::{db::{query, DbConnection}, utils};
+
+#[cfg(not(feature = "sqlite"))]
+use crate::db::query_scalar;
+
+/// Represents the account state.
+///
+/// - `Active` accounts can be used normally.
+/// - `PendingDelete` accounts can log in, but must be reactivated before playing.
+/// - `LegalHold` accounts are frozen; they can't be used, changed or deleted until the hold is lifted.
+/// - `Deleted` accounts can't be used, and can never leave this state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountState {
+    Deleted = 0,
+    Active = 1,
+    PendingDelete = 2,
+    LegalHold = 3
+}
+
+impl AccountState {
+    /// Checks if an account in this state can log in with its credentials.
+    pub fn can_login(self) -> bool {
+        matches!(self, AccountState::Active | AccountState::PendingDelete)
+    }
+
+    /// Checks if an account in this state can resume a session with a login token.
+    pub fn can_verify(self) -> bool {
+        matches!(self, AccountState::Active)
+    }
+
+    /// Checks if new devices or credentials can be registered to an account in this state.
+    pub fn can_register(self) -> bool {
+        matches!(self, AccountState::Active)
+    }
+
+    /// Checks if an account in this state can be moved to the given state.
+    pub fn can_transition(self, to: AccountState) -> bool {
+        use AccountState::*;
+
+        matches!(
+            (self, to),
+            (Active, PendingDelete) | (Active, LegalHold) | (Active, Deleted) |
+            (PendingDelete, Active) | (PendingDelete, LegalHold) | (PendingDelete, Deleted) |
+            (LegalHold, Active)
+        )
+    }
+}
+
+impl TryFrom<i32> for AccountState {
+    type Error = ();
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AccountState::Deleted),
+            1 => Ok(AccountState::Active),
+            2 => Ok(AccountState::PendingDelete),
+            3 => Ok(AccountState::LegalHold),
+            _ => Err(())
+        }
+    }
+}
+
+impl PartialEq<i32> for AccountState {
+    fn eq(&self, other: &i32) -> bool {
+        *self as i32 == *other
+    }
+}
+
+impl PartialEq<AccountState> for i32 {
+    fn eq(&self, other: &AccountState) -> bool {
+        *self == *other as i32
+    }
+}
+
+/// An error which occurs while moving an account to a new state.
+#[derive(Debug)]
+pub enum TransitionError {
+    /// The account doesn't exist.
+    NotFound,
+
+    /// The account's current state can't be moved to the requested state.
+    NotAllowed(AccountState, AccountState),
+
+    /// The database query failed.
+    Database(sqlx::Error)
+}
+
+impl From<sqlx::Error> for TransitionError {
+    fn from(error: sqlx::Error) -> Self {
+        TransitionError::Database(error)
+    }
+}
+
+/// Moves an account to a new state.
+///
+/// The transition is written to the account's state history, along with the reason.
+pub async fn transition(
+    db: &mut DbConnection,
+    uid: i32,
+    to: AccountState,
+    reason: &str
+) -> Result<(), TransitionError> {
+    let mut tx = db.begin().await?;
+
+    // Fetch the account's current state.
+    #[cfg(not(feature = "sqlite"))]
+    let from = query_scalar!(
+        "SELECT `state` FROM `accounts` WHERE `uid` = ? FOR UPDATE",
+        "SELECT state FROM accounts WHERE uid = $1 FOR UPDATE",
+        uid
+    ).fetch_optional(&mut *tx).await?;
+
+    // SQLite has no row locks, and a transaction only takes the database's write lock on its first write.
+    // A no-op write takes the lock before the state is read, so no other transition can change it meanwhile.
+    #[cfg(feature = "sqlite")]
+    sqlx::query!(
+        "UPDATE accounts SET state = state WHERE uid = $1",
+        uid
+    ).execute(&mut *tx).await?;
+
+    #[cfg(feature = "sqlite")]
+    let from = sqlx::query_scalar!(
+        "SELECT state FROM accounts WHERE uid = $1",
+        uid
+    ).fetch_optional(&mut *tx).await?;
+
+    let from = match from {
+        Some(state) => state,
+        None => return Err(TransitionError::NotFound)
+    };
+
+    // Unknown states can only be corrected by moving the account to a known state.
+    if let Ok(from) = AccountState::try_from(from) {
+        if !from.can_transition(to) {
+            return Err(TransitionError::NotAllowed(from, to));
+        }
+    }
+
+    // Update the account, and record the transition.
+    let to_state = to as i32;
+    query!(
+        "UPDATE `accounts` SET `state` = ? WHERE `uid` = ?",
+        "UPDATE accounts SET state = $1 WHERE uid = $2",
+        to_state, uid
+    ).execute(&mut *tx).await?;
+    query!(
+        "INSERT INTO `account_state_history` (`uid`, `from_state`, `to_state`, `reason`, `epoch_changed`) VALUES (?, ?, ?, ?, ?)",
+        "INSERT INTO account_state_history (uid, from_state, to_state, reason, epoch_changed) VALUES ($1, $2, $3, $4, $5)",
+        uid, from, to_state, reason, utils::current_time()
+    ).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::{path::Path, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, bail};
+use log::{error, info, warn};
+use rocket::{fairing::AdHoc, http::{uri::Origin, Method}, response::content::RawJson, route::{Handler, Outcome}, Data, Request, Route};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{config::Config, utils};
+
+/// Stubs are ranked after every real route, so a stub never shadows one.
+const STUB_RANK: isize = 100;
+
+/// The game biz used when it can't be determined from the request.
+const DEFAULT_GAME_BIZ: &str = "hk4e_global";
+
+/// A canned response for an endpoint which `pancake` doesn't implement.
+#[derive(Deserialize)]
+struct Stub {
+    /// The HTTP method, such as `GET` or `POST`.
+    #[serde(default = "default_method")]
+    method: String,
+
+    /// The path of the endpoint, relative to where stubs are mounted.
+    path: String,
+
+    /// The JSON body which is sent back.
+    ///
+    /// Strings may contain `{{uid}}`, `{{timestamp}}` and `{{game_biz}}`,
+    /// which are replaced when the response is sent.
+    response: Value
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+/// Sends a stub's response, filling in its template variables.
+#[derive(Clone)]
+struct StubHandler {
+    body: Arc<str>
+}
+
+#[rocket::async_trait]
+impl Handler for StubHandler {
+    async fn handle<'r>(&self, request: &'r Request<'_>, _data: Data<'r>) -> Outcome<'r> {
+        Outcome::from(request, RawJson(render(&self.body, request)))
+    }
+}
+
+/// Replaces the template variables in a stub's body.
+///
+/// The values are restricted to characters which can't break the JSON.
+fn render(body: &str, request: &Request<'_>) -> String {
+    let uid = request.query_value::<u64>("uid")
+        .and_then(Result::ok)
+        .unwrap_or_default();
+
+    // The game biz is read from the header, or the base the stub was mounted at.
+    let game_biz = request.headers().get_one("x-rpc-game_biz")
+        .or_else(|| request.route().map(|route| route.uri.base().trim_start_matches('/')))
+        .filter(|biz| !biz.is_empty() && biz.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(DEFAULT_GAME_BIZ);
+
+    body.replace("{{uid}}", &uid.to_string())
+        .replace("{{timestamp}}", &utils::current_time().to_string())
+        .replace("{{game_biz}}", game_biz)
+}
+
+/// Reads every stub from a single file.
+///
+/// Each file contains a JSON array of stubs.
+fn load_file(path: &Path) -> anyhow::Result<Vec<Route>> {
+    let content = std::fs::read_to_string(path)?;
+    let stubs: Vec<Stub> = serde_json::from_str(&content)?;
+
+    stubs.into_iter().map(|stub| {
+        let method = Method::from_str(&stub.method.to_uppercase())
+            .map_err(|_| anyhow!("invalid method '{}'", stub.method))?;
+        if Origin::parse_route(&stub.path).is_err() {
+            bail!("invalid path '{}'", stub.path);
+        }
+
+        let handler = StubHandler { body: stub.response.to_string().into() };
+        Ok(Route::ranked(STUB_RANK, method, &stub.path, handler))
+    }).collect()
+}
+
+/// Loads the stub routes from every `.json` file in a directory.
+///
+/// Files which can't be loaded are logged and skipped.
+pub fn load(directory: &str) -> Vec<Route> {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("No stubs were loaded from {}: {}", directory, err);
+            return Vec::new();
+        }
+    };
+
+    let mut routes = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        match load_file(&path) {
+            Ok(stubs) => routes.extend(stubs),
+            Err(err) => error!("Failed to load the stubs in {}: {:#}", path.display(), err)
+        }
+    }
+
+    info!("Loaded {} stub route(s) from {}", routes.len(), directory);
+    routes
+}
+
+/// Creates a fairing which mounts the stubs from the configured directory.
+///
+/// Stubs are mounted at the root, and under each game biz.
+/// This should be attached after the configuration fairing.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Stubs", |rocket| async {
+        let Some(config) = rocket.state::<Config>() else {
+            error!("Failed to load the stubs, as the configuration isn't loaded");
+            return Err(rocket);
+        };
+
+        let stubs = load(&config.stubs.path);
+        Ok(rocket
+            .mount("/", stubs.clone())
+            .mount("/hk4e_global", stubs.clone())
+            .mount("/hk4e_cn", stubs))
+    })
+}
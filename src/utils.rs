@@ -1,12 +1,16 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use bcrypt::{BcryptError, DEFAULT_COST};
+use hmac::{Hmac, Mac};
 use rand::{distr::Alphanumeric, Rng};
 use rocket::response::content::RawJson;
 use serde::Serialize;
 use serde_json::json;
+use sha2::{Digest, Sha256};
 
 use crate::constants;
+use crate::guards::{client_key::ClientKey, language::Language};
 
 /// Uses BCrypt standard to hash the password.
 pub fn hash_password<'a>(plain_text: &'a str) -> Result<String, BcryptError> {
@@ -20,16 +24,50 @@ pub fn verify_password<'a>(plain_text: &'a str, hashed: &String) -> bool {
     bcrypt::verify(plain_text, hashed).unwrap_or(false)
 }
 
+/// Decodes a password sent by the client.
+///
+/// The password is Base64-encoded, and RSA-encrypted with the client's key if `is_crypto` is set.
+/// Returns `None` if the password couldn't be decoded or decrypted.
+pub fn decode_password(key: &ClientKey<'_>, password: &str, is_crypto: bool) -> Option<String> {
+    let password = BASE64_STANDARD.decode(password).ok()?;
+    let password = if is_crypto {
+        key.decrypt(&password)?
+    } else {
+        password
+    };
+
+    Some(String::from_utf8(password).unwrap_or_default())
+}
+
+/// Signs a message with HMAC-SHA256, returning the signature as lowercase hex.
+pub fn hmac_sign(key: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Hashes a token for storage, returning the SHA-256 digest as lowercase hex.
+///
+/// Tokens are random, so they don't need a slow hash like passwords.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 /// Returns the current UNIX timestamp in seconds.
-pub fn current_time() -> u32 {
+pub fn current_time() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("time went backwards")
-        .as_secs() as u32
+        .as_secs() as i64
 }
 
 /// Creates a JSON value for SDK-specific JSON responses.
+///
+/// The message is translated into the client's language.
 pub fn message_response(
+    language: Language,
     code: i16, 
     message: &'static str, 
     data: impl Serialize
@@ -39,7 +77,7 @@ pub fn message_response(
     RawJson(serde_json::to_string(
         &json!({
             "retcode": code,
-            "message": message,
+            "message": language.translate(message),
             "data": encoded
         })
     ).unwrap())
@@ -54,6 +92,18 @@ pub fn random_token() -> String {
     ).unwrap()
 }
 
+/// Generates a random, numeric 6-digit verification code.
+pub fn random_code() -> String {
+    format!("{:06}", rand::rng().random_range(0..1_000_000))
+}
+
+/// Checks if a string looks like a mobile number.
+///
+/// Mobile numbers are between 5 and 16 digits long.
+pub fn is_valid_mobile(mobile: &str) -> bool {
+    (5..=16).contains(&mobile.len()) && mobile.chars().all(|c| c.is_ascii_digit())
+}
+
 /// Masks a string.
 /// 
 /// A mask is a string, with the content in the middle being replaced with asterisks.
@@ -79,11 +129,4 @@ pub fn ip_to_country(address: String) -> String {
         Ok(address) => reader.lookup(address).unwrap_or("ZZ".to_string()),
         Err(_) => "ZZ".to_string()
     }
-}
-
-/// A generic system error.
-/// 
-/// Return whenever an internal server error occurs.
-pub fn system_error() -> RawJson<String> {
-    message_response(constants::RESPONSE_FAILURE, "System error; please try again later.", ())
 }
\ No newline at end of file